@@ -3,38 +3,42 @@
 
 use defmt::*;
 
-use am2301::{measure_once_timeout, Measure};
+use am2301::{Am2301, EmbassyClock, RetriedMeasure, RetryPolicy};
 
 use embassy_executor::Spawner;
-use embassy_rp::gpio::Flex;
+use embassy_rp::gpio::{Level, OutputOpenDrain};
 use embassy_rp::peripherals::PIN_21;
-use embassy_time::{Instant, Timer};
+use embassy_time::Delay;
 
 use {defmt_rtt as _, panic_probe as _};
 
 #[embassy_executor::task]
 pub async fn measure_task(pin: PIN_21) -> ! {
-    let mut pin = Flex::new(pin);
-
-    // Wait for sensor to initialized
-    Timer::after_secs(2).await;
+    // The driver waits for the sensor to initialize, and for 2s between each
+    // measure.
+    let mut sensor = Am2301::new(
+        OutputOpenDrain::new(pin, Level::High),
+        EmbassyClock,
+        Delay,
+    );
 
     loop {
-        let start = Instant::now();
-        match measure_once_timeout(&mut pin).await {
-            Ok(Measure {
-                humidity,
-                temperature,
+        match sensor.measure_with_retry(&RetryPolicy::default()).await {
+            Ok(RetriedMeasure {
+                measure: reading,
+                attempts,
             }) => {
-                info!("Temperature = {} and humidity = {}", temperature, humidity);
+                info!(
+                    "Temperature = {} and humidity = {} ({} attempts)",
+                    reading.measure.temperature_f32(),
+                    reading.measure.humidity_f32(),
+                    attempts
+                );
             }
             Err(err) => {
                 warn!("Error while measure temperature and humidity: {:?}", err)
             }
         }
-        let delay = 5 - start.elapsed().as_secs();
-        info!("Sleeping for {}s", delay);
-        Timer::after_secs(delay).await;
     }
 }
 
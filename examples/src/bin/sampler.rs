@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+
+use am2301::{Am2301, EmbassyClock, Sample, Sampler};
+
+use embassy_executor::Spawner;
+use embassy_rp::gpio::{Level, OutputOpenDrain};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::Watch;
+use embassy_time::Delay;
+
+use {defmt_rtt as _, panic_probe as _};
+
+/// Latest sample of the sensor, shared with up to 2 receiving tasks.
+static SAMPLES: Watch<CriticalSectionRawMutex, Sample, 2> = Watch::new();
+
+#[embassy_executor::task]
+async fn sampling_task(sampler: Sampler<OutputOpenDrain<'static>, EmbassyClock, Delay>) -> ! {
+    sampler.run(&SAMPLES).await
+}
+
+#[embassy_executor::task]
+async fn log_task() -> ! {
+    let mut receiver = unwrap!(SAMPLES.receiver());
+    loop {
+        let sample = receiver.changed().await;
+        match (sample.reading, sample.last_error) {
+            (_, Some(err)) => warn!("Error while measuring: {:?}", err),
+            (Some(reading), None) => info!(
+                "Temperature = {} and humidity = {} (sample #{}, {} failures so far)",
+                reading.measure.temperature_f32(),
+                reading.measure.humidity_f32(),
+                reading.sequence,
+                sample.failures
+            ),
+            (None, None) => {}
+        }
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    let sensor = Am2301::new(
+        OutputOpenDrain::new(p.PIN_21, Level::High),
+        EmbassyClock,
+        Delay,
+    );
+    unwrap!(spawner.spawn(sampling_task(Sampler::new(sensor).with_period_ms(10_000))));
+    unwrap!(spawner.spawn(log_task()));
+}
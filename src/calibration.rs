@@ -0,0 +1,253 @@
+use defmt::Format;
+
+use crate::Measure;
+
+/// Gain of 1, in parts per ten thousand.
+const UNIT_GAIN: i64 = 10_000;
+/// Version of the layout of [`Calibration::to_bytes`].
+const LAYOUT_VERSION: u8 = 1;
+
+/// Possible ways building or loading a calibration can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub enum CalibrationError {
+    /// Both calibration points share the same raw value.
+    DegeneratePoints,
+    /// The stored calibration has an unknown layout version.
+    UnknownVersion(u8),
+    /// The stored calibration is corrupted.
+    InvalidChecksum,
+    /// The gain does not fit in an `i32` or the offset in an `i16`.
+    OutOfRange,
+}
+
+/// Linear correction `gain * raw + offset` of a value in tenths of unit, in
+/// integer arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct LinearCorrection {
+    /// Gain in parts per ten thousand, 10 000 leaving the value unchanged.
+    pub gain_permyriad: i32,
+    /// Offset in tenths of unit, added after the gain.
+    pub offset_tenths: i16,
+}
+
+impl LinearCorrection {
+    /// Correction leaving the value unchanged.
+    pub const IDENTITY: Self = Self {
+        gain_permyriad: UNIT_GAIN as i32,
+        offset_tenths: 0,
+    };
+
+    /// Correction only adding `offset_tenths`.
+    pub const fn offset(offset_tenths: i16) -> Self {
+        Self {
+            gain_permyriad: UNIT_GAIN as i32,
+            offset_tenths,
+        }
+    }
+
+    /// Correction mapping two raw values to their reference values, all in
+    /// tenths of unit.
+    pub fn from_two_points(
+        (raw_low, reference_low): (i16, i16),
+        (raw_high, reference_high): (i16, i16),
+    ) -> Result<Self, CalibrationError> {
+        if raw_low == raw_high {
+            return Err(CalibrationError::DegeneratePoints);
+        }
+        let gain = (reference_high as i64 - reference_low as i64) * UNIT_GAIN
+            / (raw_high as i64 - raw_low as i64);
+        let offset = reference_low as i64 - scale(raw_low as i64, gain);
+        Ok(Self {
+            gain_permyriad: i32::try_from(gain).map_err(|_| CalibrationError::OutOfRange)?,
+            offset_tenths: i16::try_from(offset).map_err(|_| CalibrationError::OutOfRange)?,
+        })
+    }
+
+    fn apply(&self, raw: i32) -> i64 {
+        scale(raw as i64, self.gain_permyriad as i64) + self.offset_tenths as i64
+    }
+}
+
+/// `raw * gain_permyriad / 10 000`, rounded half away from zero.
+fn scale(raw: i64, gain_permyriad: i64) -> i64 {
+    let scaled = raw * gain_permyriad;
+    let half = UNIT_GAIN / 2 * scaled.signum();
+    (scaled + half) / UNIT_GAIN
+}
+
+impl Default for LinearCorrection {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Corrections applied to the measures of a given sensor, to match a
+/// reference instrument.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Format)]
+pub struct Calibration {
+    pub humidity: LinearCorrection,
+    pub temperature: LinearCorrection,
+}
+
+impl Calibration {
+    /// Length of the serialized calibration.
+    pub const SERIALIZED_LEN: usize = 14;
+
+    /// Correct `measure`, the humidity being clamped to 0-100%.
+    pub fn apply(&self, measure: Measure) -> Measure {
+        let humidity = self.humidity.apply(measure.humidity_decipercent as i32);
+        let temperature = self
+            .temperature
+            .apply(measure.temperature_decicelsius as i32);
+        Measure {
+            humidity_decipercent: humidity.clamp(0, 1000) as u16,
+            temperature_decicelsius: temperature.clamp(i16::MIN as i64, i16::MAX as i64) as i16,
+        }
+    }
+
+    /// Serialize the calibration, to store it in flash for instance: a layout
+    /// version, the gains (little endian) and offsets of humidity then
+    /// temperature, and a checksum.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut bytes = [0u8; Self::SERIALIZED_LEN];
+        bytes[0] = LAYOUT_VERSION;
+        bytes[1..5].copy_from_slice(&self.humidity.gain_permyriad.to_le_bytes());
+        bytes[5..7].copy_from_slice(&self.humidity.offset_tenths.to_le_bytes());
+        bytes[7..11].copy_from_slice(&self.temperature.gain_permyriad.to_le_bytes());
+        bytes[11..13].copy_from_slice(&self.temperature.offset_tenths.to_le_bytes());
+        bytes[13] = checksum(&bytes[..13]);
+        bytes
+    }
+
+    /// Deserialize a calibration written by [`Calibration::to_bytes`].
+    pub fn from_bytes(bytes: &[u8; Self::SERIALIZED_LEN]) -> Result<Self, CalibrationError> {
+        if bytes[0] != LAYOUT_VERSION {
+            return Err(CalibrationError::UnknownVersion(bytes[0]));
+        }
+        if checksum(&bytes[..13]) != bytes[13] {
+            return Err(CalibrationError::InvalidChecksum);
+        }
+        let i32_at = |idx: usize| {
+            i32::from_le_bytes([bytes[idx], bytes[idx + 1], bytes[idx + 2], bytes[idx + 3]])
+        };
+        let i16_at = |idx: usize| i16::from_le_bytes([bytes[idx], bytes[idx + 1]]);
+        Ok(Self {
+            humidity: LinearCorrection {
+                gain_permyriad: i32_at(1),
+                offset_tenths: i16_at(5),
+            },
+            temperature: LinearCorrection {
+                gain_permyriad: i32_at(7),
+                offset_tenths: i16_at(11),
+            },
+        })
+    }
+}
+
+/// Same checksum as the sensor frames, the wrapping sum of the bytes, inverted
+/// so that erased flash (all 0xFF) or zeroed memory does not pass it.
+fn checksum(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |acc, &byte| acc.wrapping_add(byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::measure;
+
+    #[test]
+    fn default_calibration_is_identity() {
+        assert_eq!(
+            Calibration::default().apply(measure(658, -269)),
+            measure(658, -269)
+        );
+    }
+
+    #[test]
+    fn apply_offsets() {
+        let calibration = Calibration {
+            humidity: LinearCorrection::offset(-20),
+            temperature: LinearCorrection::offset(3),
+        };
+
+        assert_eq!(calibration.apply(measure(658, 269)), measure(638, 272));
+        assert_eq!(calibration.apply(measure(10, -5)), measure(0, -2));
+    }
+
+    #[test]
+    fn two_point_calibration() {
+        // Reads 1.0°C at 0°C and 49.0°C at 50°C.
+        let temperature = LinearCorrection::from_two_points((10, 0), (490, 500)).unwrap();
+        let calibration = Calibration {
+            temperature,
+            ..Default::default()
+        };
+
+        assert_eq!(temperature.gain_permyriad, 10_416);
+        assert_eq!(
+            calibration.apply(measure(500, 10)).temperature_decicelsius,
+            0
+        );
+        assert_eq!(
+            calibration.apply(measure(500, 490)).temperature_decicelsius,
+            500
+        );
+        assert_eq!(
+            calibration.apply(measure(500, 250)).temperature_decicelsius,
+            250
+        );
+        assert_eq!(
+            LinearCorrection::from_two_points((10, 0), (10, 500)),
+            Err(CalibrationError::DegeneratePoints)
+        );
+    }
+
+    #[test]
+    fn two_point_calibration_maps_raw_points_exactly() {
+        // 1 * 16 666 / 10 000 rounds to 2, a truncated offset would be off by
+        // one at the low point.
+        let correction = LinearCorrection::from_two_points((1, 0), (4, 5)).unwrap();
+
+        assert_eq!(correction.offset_tenths, -2);
+        assert_eq!(correction.apply(1), 0);
+        assert_eq!(correction.apply(4), 5);
+    }
+
+    #[test]
+    fn two_point_calibration_out_of_range() {
+        assert_eq!(
+            LinearCorrection::from_two_points((10_000, 0), (10_001, 10)),
+            Err(CalibrationError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        let calibration = Calibration {
+            humidity: LinearCorrection {
+                gain_permyriad: 9_800,
+                offset_tenths: 15,
+            },
+            temperature: LinearCorrection::offset(-4),
+        };
+
+        let bytes = calibration.to_bytes();
+
+        assert_eq!(Calibration::from_bytes(&bytes), Ok(calibration));
+    }
+
+    #[test]
+    fn reject_corrupted_or_erased_storage() {
+        let mut bytes = Calibration::default().to_bytes();
+        bytes[3] ^= 1;
+
+        assert_eq!(
+            Calibration::from_bytes(&bytes),
+            Err(CalibrationError::InvalidChecksum)
+        );
+        assert_eq!(
+            Calibration::from_bytes(&[0xff; Calibration::SERIALIZED_LEN]),
+            Err(CalibrationError::UnknownVersion(0xff))
+        );
+    }
+}
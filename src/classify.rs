@@ -0,0 +1,151 @@
+use defmt::Format;
+
+/// Half the difference between the nominal 70us and 26-28us high pulses.
+const NOMINAL_MARGIN_US: u8 = 22;
+/// Below this margin, pulses of 0 and 1 bits cannot be told apart reliably.
+const MIN_MARGIN_US: u8 = 4;
+
+/// How the high pulses of the data bits were classified into 0s and 1s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct BitClassification {
+    /// High pulses longer than this, in µs, encode a 1.
+    pub threshold_us: u8,
+    /// Distance in µs between the threshold and the closest high pulse.
+    pub margin_us: u8,
+    /// Margin as a percentage of the nominal margin of the protocol, capped at
+    /// 100.
+    pub confidence: u8,
+}
+
+/// Threshold expected from the 80us handshake high pulse: 50us nominally,
+/// scaled by the clock drift and line characteristics affecting the handshake.
+pub(crate) fn reference_threshold_us(handshake_high_us: u8) -> u8 {
+    (handshake_high_us as u16 * 5 / 8) as u8
+}
+
+/// Bits encoded by the given high pulses, against the reference threshold.
+pub(crate) fn reference_bits(high_us: &[u8], handshake_high_us: u8) -> [u8; 40] {
+    let threshold_us = reference_threshold_us(handshake_high_us);
+    let mut bits = [0u8; 40];
+    for (bit, &high_us) in bits.iter_mut().zip(high_us) {
+        *bit = (high_us > threshold_us) as u8;
+    }
+    bits
+}
+
+/// Classify the high pulses of the 40 data bits.
+///
+/// When the widest gap between the sorted pulses contains the reference
+/// threshold derived from the handshake, the two populations are split in the
+/// middle of that gap, otherwise the reference threshold is used as is. Fails
+/// with the attempted classification when some pulse is too close to the
+/// threshold, meaning that the populations of 0s and 1s overlap.
+pub(crate) fn classify_pulses(
+    high_us: &[u8; 40],
+    handshake_high_us: u8,
+) -> Result<([u8; 40], BitClassification), BitClassification> {
+    let reference_us = reference_threshold_us(handshake_high_us);
+
+    let mut sorted = *high_us;
+    sorted.sort_unstable();
+    let (low, high) = sorted
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .max_by_key(|(low, high)| high - low)
+        .unwrap_or((0, 0));
+    let threshold_us = if low <= reference_us && reference_us < high {
+        ((low as u16 + high as u16) / 2) as u8
+    } else {
+        reference_us
+    };
+
+    let margin_us = high_us
+        .iter()
+        .map(|&pulse| pulse.abs_diff(threshold_us))
+        .min()
+        .unwrap_or(0);
+    let classification = BitClassification {
+        threshold_us,
+        margin_us,
+        confidence: (margin_us as u16 * 100 / NOMINAL_MARGIN_US as u16).min(100) as u8,
+    };
+    if margin_us < MIN_MARGIN_US {
+        return Err(classification);
+    }
+
+    let mut bits = [0u8; 40];
+    for (bit, &pulse) in bits.iter_mut().zip(high_us) {
+        *bit = (pulse > threshold_us) as u8;
+    }
+    Ok((bits, classification))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::DATASHEET_BITS;
+
+    fn pulses(bits: [u8; 40], zero_us: u8, one_us: u8) -> [u8; 40] {
+        bits.map(|bit| if bit == 1 { one_us } else { zero_us })
+    }
+
+    #[test]
+    fn nominal_pulses_split_in_the_middle() {
+        let res = classify_pulses(&pulses(DATASHEET_BITS, 26, 70), 80);
+
+        assert_eq!(
+            res,
+            Ok((
+                DATASHEET_BITS,
+                BitClassification {
+                    threshold_us: 48,
+                    margin_us: 22,
+                    confidence: 100,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn threshold_follows_clock_drift() {
+        // A clock running 40% slow measures 16us and 42us pulses, and a 48us
+        // handshake: a fixed 50us threshold would only see 0s.
+        let res = classify_pulses(&pulses(DATASHEET_BITS, 16, 42), 48);
+
+        assert!(
+            matches!(res, Ok((bits, BitClassification { threshold_us: 29, .. })) if bits == DATASHEET_BITS)
+        );
+    }
+
+    #[test]
+    fn single_population_uses_reference_threshold() {
+        let res = classify_pulses(&pulses([0; 40], 27, 70), 80);
+
+        assert!(
+            matches!(res, Ok((bits, BitClassification { threshold_us: 50, margin_us: 23, .. })) if bits == [0; 40])
+        );
+    }
+
+    #[test]
+    fn overlapping_populations_fail() {
+        let mut high_us = pulses(DATASHEET_BITS, 46, 52);
+        high_us[3] = 50;
+
+        let res = classify_pulses(&high_us, 80);
+
+        assert!(matches!(
+            res,
+            Err(BitClassification {
+                margin_us: 0..=3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reference_bits_of_a_partial_frame() {
+        let bits = reference_bits(&[26, 70, 70], 80);
+
+        assert_eq!(bits[..4], [0, 1, 1, 0]);
+    }
+}
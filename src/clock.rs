@@ -0,0 +1,29 @@
+#[cfg(not(feature = "std"))]
+use embassy_time::{block_for, Duration, Instant};
+
+/// Source of time used while bit-banging the sensor protocol.
+pub trait Clock {
+    /// Current monotonic time, in microseconds.
+    fn now_micros(&mut self) -> u64;
+
+    /// Busy-wait for the given number of microseconds.
+    fn delay_us(&mut self, us: u32);
+}
+
+/// [`Clock`] backed by the `embassy-time` driver of the target. Not available
+/// with the `std` feature, which provides no such driver: use
+/// [`crate::StdClock`] instead.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug)]
+pub struct EmbassyClock;
+
+#[cfg(not(feature = "std"))]
+impl Clock for EmbassyClock {
+    fn now_micros(&mut self) -> u64 {
+        Instant::now().as_micros()
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        block_for(Duration::from_micros(us as u64));
+    }
+}
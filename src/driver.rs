@@ -0,0 +1,374 @@
+use embassy_time::{Duration, Instant};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+use futures_util::stream::{unfold, Stream};
+
+use crate::calibration::Calibration;
+use crate::clock::Clock;
+use crate::reading::Reading;
+use crate::retry::{RetriedMeasure, RetryPolicy};
+use crate::{measure_once_blocking, MeasureError, SensorModel};
+
+/// Time for the sensor to stabilize after power-up, as per its datasheet.
+const POWER_UP_DELAY_US: u64 = 2_000_000;
+/// Minimum interval between two measures, as per the sensor datasheet.
+pub(crate) const MIN_INTERVAL_US: u64 = 2_000_000;
+
+/// What [`Am2301::measure`] does when called before the sensor is ready.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntervalPolicy {
+    /// Wait until the sensor can be measured again.
+    Wait,
+    /// Return the last successful measure, or
+    /// [`MeasureError::TooSoonError`] if there is none.
+    Cached,
+    /// Return [`MeasureError::TooSoonError`].
+    Error,
+}
+
+/// AM2301 sensor owning its pin, making sure it is never measured more often
+/// than its datasheet allows.
+///
+/// The power-up settling delay is counted from the creation of the driver.
+pub struct Am2301<P, C, D> {
+    pin: P,
+    clock: C,
+    delay: D,
+    policy: IntervalPolicy,
+    model: SensorModel,
+    calibration: Calibration,
+    ready_at_us: u64,
+    last_measure_at: Option<Instant>,
+    last_reading: Option<Reading>,
+    sequence: u32,
+}
+
+impl<P, C, D> Am2301<P, C, D>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+    D: DelayNs,
+{
+    /// Create a driver for the AM2301 sensor connected to the open-drain
+    /// `pin`, waiting for the sensor to be ready when measured too early.
+    pub fn new(pin: P, mut clock: C, delay: D) -> Self {
+        let ready_at_us = clock.now_micros() + POWER_UP_DELAY_US;
+        Self {
+            pin,
+            clock,
+            delay,
+            policy: IntervalPolicy::Wait,
+            model: SensorModel::Am2301,
+            calibration: Calibration::default(),
+            ready_at_us,
+            last_measure_at: None,
+            last_reading: None,
+            sequence: 0,
+        }
+    }
+
+    /// Change what happens when the sensor is measured too early.
+    pub fn with_policy(mut self, policy: IntervalPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Drive another sensor sharing the AM2301 protocol.
+    pub fn with_model(mut self, model: SensorModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Correct every measure of the sensor with `calibration`.
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Retrieve a measure from the sensor, applying the [`IntervalPolicy`] if
+    /// it cannot be measured yet.
+    pub async fn measure(&mut self) -> Result<Reading, MeasureError> {
+        if self.clock.now_micros() < self.ready_at_us {
+            match self.policy {
+                IntervalPolicy::Wait => self.wait_until_ready().await,
+                IntervalPolicy::Cached => {
+                    return self.last_reading.ok_or(MeasureError::TooSoonError);
+                }
+                IntervalPolicy::Error => return Err(MeasureError::TooSoonError),
+            }
+        }
+        self.measure_now()
+    }
+
+    /// Measure the sensor forever, every `period_ms` clamped to the 2s minimum
+    /// interval of the sensor. Always waits for the sensor to be ready,
+    /// whatever the [`IntervalPolicy`].
+    pub fn stream(
+        &mut self,
+        period_ms: u32,
+    ) -> impl Stream<Item = Result<Reading, MeasureError>> + '_ {
+        let period_us = (period_ms as u64 * 1_000).max(MIN_INTERVAL_US);
+        unfold(self, move |sensor| async move {
+            sensor.wait_until_ready().await;
+            let reading = sensor.measure_now();
+            sensor.postpone(period_us - MIN_INTERVAL_US);
+            Some((reading, sensor))
+        })
+    }
+
+    async fn wait_until_ready(&mut self) {
+        // Periods may exceed what a single `delay_us` call can wait for.
+        let mut now = self.clock.now_micros();
+        while now < self.ready_at_us {
+            let remaining_us = (self.ready_at_us - now).min(u32::MAX as u64);
+            self.delay.delay_us(remaining_us as u32).await;
+            now = self.clock.now_micros();
+        }
+    }
+
+    fn measure_now(&mut self) -> Result<Reading, MeasureError> {
+        let start = self.clock.now_micros();
+        self.ready_at_us = start + MIN_INTERVAL_US;
+        self.last_measure_at = Some(Instant::from_micros(start));
+
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let measure = measure_once_blocking(&mut self.pin, &mut self.clock, self.model)?;
+        let reading = Reading {
+            measure: self.calibration.apply(measure),
+            at: Instant::from_micros(self.clock.now_micros()),
+            sequence,
+        };
+        self.last_reading = Some(reading);
+        Ok(reading)
+    }
+
+    /// Retrieve a measure from the sensor, retrying failed measures according
+    /// to `policy`. Returns the last error if no attempt succeeded.
+    pub async fn measure_with_retry(
+        &mut self,
+        policy: &RetryPolicy,
+    ) -> Result<RetriedMeasure<Reading>, MeasureError> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.measure().await {
+                Ok(measure) => return Ok(RetriedMeasure { measure, attempts }),
+                Err(err) if policy.should_retry(attempts, &err) => {
+                    self.delay.delay_ms(policy.backoff_ms()).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Push back the next measure by `us`, for [`IntervalPolicy::Wait`] to
+    /// wait longer than the minimum interval.
+    pub(crate) fn postpone(&mut self, us: u64) {
+        self.ready_at_us += us;
+    }
+
+    /// When the sensor was last triggered, whether the measure succeeded or not.
+    pub fn last_measure_at(&self) -> Option<Instant> {
+        self.last_measure_at
+    }
+
+    /// Time elapsed since `reading` was taken, according to the clock of the
+    /// driver.
+    pub fn age(&mut self, reading: &Reading) -> Duration {
+        reading.age_at(Instant::from_micros(self.clock.now_micros()))
+    }
+
+    /// Release the pin, clock and delay owned by the driver.
+    pub fn free(self) -> (P, C, D) {
+        (self.pin, self.clock, self.delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::pin::pin;
+
+    use embassy_futures::block_on;
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::calibration::LinearCorrection;
+    use crate::mock::{Simulation, Waveform, DATASHEET_FRAME};
+    use crate::Measure;
+
+    #[test]
+    fn first_measure_waits_for_power_up() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+
+        let res = block_on(sensor.measure());
+
+        assert!(res.is_ok());
+        assert!(sim.now_us() >= POWER_UP_DELAY_US);
+        assert_eq!(
+            sensor.last_measure_at(),
+            Some(Instant::from_micros(POWER_UP_DELAY_US))
+        );
+    }
+
+    #[test]
+    fn consecutive_measures_are_spaced_by_min_interval() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+
+        let _ = block_on(sensor.measure());
+        let first = sensor.last_measure_at().unwrap();
+        let res = block_on(sensor.measure());
+        let second = sensor.last_measure_at().unwrap();
+
+        assert!(res.is_ok());
+        assert!(second.as_micros() - first.as_micros() >= MIN_INTERVAL_US);
+    }
+
+    #[test]
+    fn readings_are_timestamped_and_numbered() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME))
+            .with_first_responses(Waveform::corrupted_datasheet(), 1);
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+
+        let failed = block_on(sensor.measure());
+        let res = block_on(sensor.measure());
+
+        assert!(failed.is_err());
+        let Ok(reading) = res else {
+            panic!();
+        };
+        assert_eq!(reading.sequence, 1);
+        assert_eq!(reading.at.as_micros(), sim.now_us());
+        assert!(reading.at > sensor.last_measure_at().unwrap());
+
+        Clock::delay_us(&mut sim.clock(), 1_500);
+        assert_eq!(sensor.age(&reading), Duration::from_micros(1_500));
+    }
+
+    #[test]
+    fn stream_readings_at_the_given_period() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor =
+            Am2301::new(sim.pin(), sim.clock(), sim.clock()).with_policy(IntervalPolicy::Error);
+        let mut readings = pin!(sensor.stream(3_000));
+
+        let [first, second, third] = [(); 3].map(|_| block_on(readings.next()));
+
+        let at = [first, second, third].map(|reading| match reading {
+            Some(Ok(reading)) => reading.at.as_micros(),
+            _ => panic!(),
+        });
+        assert_eq!(at[1] - at[0], 3_000_000);
+        assert_eq!(at[2] - at[1], 3_000_000);
+    }
+
+    #[test]
+    fn stream_readings_hours_apart() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+        let mut readings = pin!(sensor.stream(6 * 3_600_000));
+
+        let first = block_on(readings.next());
+        let second = block_on(readings.next());
+
+        let (Some(Ok(first)), Some(Ok(second))) = (first, second) else {
+            panic!();
+        };
+        assert_eq!(
+            second.at.as_micros() - first.at.as_micros(),
+            6 * 3_600_000_000
+        );
+    }
+
+    #[test]
+    fn stream_period_is_clamped_to_min_interval() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+        let mut readings = pin!(sensor.stream(500));
+
+        let first = block_on(readings.next());
+        let second = block_on(readings.next());
+
+        let (Some(Ok(first)), Some(Ok(second))) = (first, second) else {
+            panic!();
+        };
+        assert_eq!(
+            second.at.as_micros() - first.at.as_micros(),
+            MIN_INTERVAL_US
+        );
+    }
+
+    #[test]
+    fn error_policy_returns_too_soon() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor =
+            Am2301::new(sim.pin(), sim.clock(), sim.clock()).with_policy(IntervalPolicy::Error);
+
+        let res = block_on(sensor.measure());
+
+        assert!(matches!(res, Err(MeasureError::TooSoonError)));
+        assert!(sensor.last_measure_at().is_none());
+    }
+
+    #[test]
+    fn cached_policy_returns_last_measure() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+
+        let first = block_on(sensor.measure());
+        let mut sensor = sensor.with_policy(IntervalPolicy::Cached);
+        let now = sim.now_us();
+        let cached = block_on(sensor.measure());
+
+        assert!(matches!((first, cached), (Ok(first), Ok(cached)) if first == cached));
+        assert_eq!(sim.now_us(), now);
+    }
+
+    #[test]
+    fn cached_policy_without_previous_measure() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor =
+            Am2301::new(sim.pin(), sim.clock(), sim.clock()).with_policy(IntervalPolicy::Cached);
+
+        let res = block_on(sensor.measure());
+
+        assert!(matches!(res, Err(MeasureError::TooSoonError)));
+    }
+
+    #[test]
+    fn calibration_applies_to_measures() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensor =
+            Am2301::new(sim.pin(), sim.clock(), sim.clock()).with_calibration(Calibration {
+                humidity: LinearCorrection::offset(-8),
+                temperature: LinearCorrection::offset(3),
+            });
+
+        let res = block_on(sensor.measure());
+
+        assert!(matches!(
+            res,
+            Ok(Reading {
+                measure: Measure {
+                    humidity_decipercent: 650,
+                    temperature_decicelsius: 272,
+                },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn measure_with_retry_recovers_from_checksum_error() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME))
+            .with_first_responses(Waveform::corrupted_datasheet(), 1);
+        let mut sensor = Am2301::new(sim.pin(), sim.clock(), sim.clock());
+
+        let res = block_on(sensor.measure_with_retry(&RetryPolicy::default()));
+
+        assert!(matches!(res, Ok(RetriedMeasure { attempts: 2, .. })));
+    }
+}
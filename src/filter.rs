@@ -0,0 +1,204 @@
+use defmt::Format;
+use embassy_time::Instant;
+
+use crate::driver::MIN_INTERVAL_US;
+use crate::{Measure, MeasureError};
+
+/// What [`SpikeFilter::filter`] does with a measure changing faster than
+/// physically plausible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub enum SpikePolicy {
+    /// Fail with [`MeasureError::SpikeError`].
+    Reject,
+    /// Return the last accepted measure instead, flagged as a spike.
+    HoldLast,
+    /// Return the measure, flagged as a spike.
+    Flag,
+}
+
+/// Measure that went through a [`SpikeFilter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct FilteredMeasure {
+    pub measure: Measure,
+    /// Whether the measure read from the sensor exceeded the rate limits.
+    pub spike: bool,
+}
+
+/// Detects bit flips passing the checksum, by bounding how fast humidity and
+/// temperature can change between two measures.
+///
+/// Limits are rates, so that a genuine change eventually gets accepted even if
+/// measures failed in the meantime.
+#[derive(Clone, Copy, Debug)]
+pub struct SpikeFilter {
+    policy: SpikePolicy,
+    max_humidity_decipercent_per_s: u16,
+    max_temperature_decicelsius_per_s: u16,
+    last: Option<(Measure, Instant)>,
+}
+
+impl SpikeFilter {
+    /// Create a filter allowing up to 5%/s of humidity and 1°C/s of
+    /// temperature changes.
+    pub fn new(policy: SpikePolicy) -> Self {
+        Self {
+            policy,
+            max_humidity_decipercent_per_s: 50,
+            max_temperature_decicelsius_per_s: 10,
+            last: None,
+        }
+    }
+
+    /// Change the maximum rates of change, in tenths of % and of degree
+    /// Celsius per second.
+    pub fn with_limits(
+        mut self,
+        max_humidity_decipercent_per_s: u16,
+        max_temperature_decicelsius_per_s: u16,
+    ) -> Self {
+        self.max_humidity_decipercent_per_s = max_humidity_decipercent_per_s;
+        self.max_temperature_decicelsius_per_s = max_temperature_decicelsius_per_s;
+        self
+    }
+
+    /// Check `measure`, taken at `at`, against the last accepted measure,
+    /// applying the [`SpikePolicy`] when it changed too fast. The first
+    /// measure is always accepted.
+    pub fn filter(
+        &mut self,
+        measure: Measure,
+        at: Instant,
+    ) -> Result<FilteredMeasure, MeasureError> {
+        let Some((last, last_at)) = self.last else {
+            self.last = Some((measure, at));
+            return Ok(FilteredMeasure {
+                measure,
+                spike: false,
+            });
+        };
+
+        let elapsed_ms = at
+            .saturating_duration_since(last_at)
+            .as_millis()
+            .max(MIN_INTERVAL_US / 1_000);
+        let allowed = |rate: u16| rate as u64 * elapsed_ms / 1_000;
+        let humidity_delta = measure
+            .humidity_decipercent
+            .abs_diff(last.humidity_decipercent);
+        let temperature_delta = measure
+            .temperature_decicelsius
+            .abs_diff(last.temperature_decicelsius);
+        if humidity_delta as u64 <= allowed(self.max_humidity_decipercent_per_s)
+            && temperature_delta as u64 <= allowed(self.max_temperature_decicelsius_per_s)
+        {
+            self.last = Some((measure, at));
+            return Ok(FilteredMeasure {
+                measure,
+                spike: false,
+            });
+        }
+
+        match self.policy {
+            SpikePolicy::Reject => Err(MeasureError::SpikeError {
+                rejected: measure,
+                last,
+            }),
+            SpikePolicy::HoldLast => Ok(FilteredMeasure {
+                measure: last,
+                spike: true,
+            }),
+            SpikePolicy::Flag => Ok(FilteredMeasure {
+                measure,
+                spike: true,
+            }),
+        }
+    }
+
+    /// Last measure accepted by the filter.
+    pub fn last_accepted(&self) -> Option<Measure> {
+        self.last.map(|(measure, _)| measure)
+    }
+
+    /// Retrieve a measure from the sensor connected to `pin` with
+    /// [`crate::measure_once_timeout`], and filter it.
+    #[cfg(feature = "rp2040")]
+    pub async fn measure(
+        &mut self,
+        pin: &mut embassy_rp::gpio::Flex<'_>,
+    ) -> Result<FilteredMeasure, MeasureError> {
+        let measure = crate::measure_once_timeout(pin).await?;
+        self.filter(measure, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::measure;
+
+    fn at_s(secs: u64) -> Instant {
+        Instant::from_secs(secs)
+    }
+
+    #[test]
+    fn accept_gradual_changes() {
+        let mut filter = SpikeFilter::new(SpikePolicy::Reject);
+
+        assert!(filter.filter(measure(500, 200), at_s(0)).is_ok());
+        let res = filter.filter(measure(560, 215), at_s(2));
+
+        assert_eq!(
+            res.ok(),
+            Some(FilteredMeasure {
+                measure: measure(560, 215),
+                spike: false
+            })
+        );
+        assert_eq!(filter.last_accepted(), Some(measure(560, 215)));
+    }
+
+    #[test]
+    fn reject_temperature_jump() {
+        let mut filter = SpikeFilter::new(SpikePolicy::Reject);
+
+        filter.filter(measure(500, 200), at_s(0)).ok();
+        let res = filter.filter(measure(500, 320), at_s(2));
+
+        assert!(matches!(res, Err(MeasureError::SpikeError { .. })));
+        assert_eq!(filter.last_accepted(), Some(measure(500, 200)));
+    }
+
+    #[test]
+    fn hold_last_or_flag_spikes() {
+        let mut hold = SpikeFilter::new(SpikePolicy::HoldLast);
+        let mut flag = SpikeFilter::new(SpikePolicy::Flag);
+        for filter in [&mut hold, &mut flag] {
+            filter.filter(measure(500, 200), at_s(0)).ok();
+        }
+
+        assert_eq!(
+            hold.filter(measure(900, 200), at_s(2)).ok(),
+            Some(FilteredMeasure {
+                measure: measure(500, 200),
+                spike: true
+            })
+        );
+        assert_eq!(
+            flag.filter(measure(900, 200), at_s(2)).ok(),
+            Some(FilteredMeasure {
+                measure: measure(900, 200),
+                spike: true
+            })
+        );
+        assert_eq!(flag.last_accepted(), Some(measure(500, 200)));
+    }
+
+    #[test]
+    fn allowed_change_grows_with_time() {
+        let mut filter = SpikeFilter::new(SpikePolicy::Reject).with_limits(50, 5);
+
+        filter.filter(measure(500, 200), at_s(0)).ok();
+        assert!(filter.filter(measure(500, 260), at_s(2)).is_err());
+        assert!(filter.filter(measure(500, 260), at_s(12)).is_ok());
+    }
+}
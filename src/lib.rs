@@ -1,59 +1,206 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(all(feature = "std", feature = "rp2040"))]
+compile_error!(
+    "the `std` and `rp2040` features are exclusive, \
+     enable `std` with `default-features = false`"
+);
+
+mod calibration;
+mod classify;
+mod clock;
+mod driver;
+mod filter;
+#[cfg(feature = "std")]
+mod linux;
 mod measure;
+mod measure_async;
+#[cfg(test)]
+mod mock;
+mod model;
+mod multi;
+mod psychrometrics;
+mod raw;
+mod reading;
+mod retry;
+#[cfg(feature = "rp2040")]
+mod rp2040;
+#[cfg(feature = "rp2040")]
+mod rp2040_pio;
+mod sampler;
+mod smoothing;
+mod stats;
+mod units;
 
 use defmt::Format;
-use embassy_rp::gpio::Flex;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
 use measure::ReadBitsError;
 
-enum ProcessResponseError {
-    InvalidChecksumError,
-    InvalidNumberOfBits,
+pub use calibration::{Calibration, CalibrationError, LinearCorrection};
+pub use classify::BitClassification;
+pub use clock::Clock;
+#[cfg(not(feature = "std"))]
+pub use clock::EmbassyClock;
+pub use driver::{Am2301, IntervalPolicy};
+pub use filter::{FilteredMeasure, SpikeFilter, SpikePolicy};
+#[cfg(feature = "std")]
+pub use linux::{CdevError, CdevOpenDrain, StdClock, StdDelay};
+pub use model::SensorModel;
+pub use multi::{Am2301Array, SensorId};
+pub use raw::{Pulse, RawFrame, FRAME_EDGES};
+pub use reading::Reading;
+pub use retry::{measure_with_retry, RetriedMeasure, RetryPolicy};
+#[cfg(feature = "rp2040")]
+pub use rp2040::FlexOpenDrain;
+#[cfg(feature = "rp2040")]
+pub use rp2040_pio::PioAm2301;
+pub use sampler::{Sample, Sampler};
+pub use smoothing::{ExponentialAverage, MedianFilter, MovingAverage, Smoothing};
+pub use stats::{PulseStats, SignalStats};
+pub use units::{Celsius, Fahrenheit, HumidityFraction, Kelvin, RelativeHumidity};
+
+#[cfg(feature = "rp2040")]
+use embassy_rp::gpio::Flex;
+#[cfg(feature = "rp2040")]
+use embassy_time::Delay;
+
+/// Possible ways decoding a frame can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub enum ProcessResponseError {
+    /// The checksum of the frame does not match its content.
+    InvalidChecksumError {
+        /// Checksum computed from the first 4 bytes of the frame.
+        expected: u8,
+        /// Checksum held by the last byte of the frame.
+        received: u8,
+        /// Raw bytes of the frame.
+        frame: [u8; 5],
+    },
+    /// A bit of the frame is neither 0 nor 1.
+    InvalidBit {
+        /// Index of the bit, 0 being the MSB of the first byte.
+        index: u8,
+    },
+    /// The frame only holds 0s or 1s, as read on a line stuck low or high.
+    StuckLine {
+        /// Raw bytes of the frame.
+        frame: [u8; 5],
+    },
+    /// The decoded values are outside of the measuring range of the sensor.
+    OutOfRange {
+        /// Decoded humidity, in tenths of %.
+        humidity_decipercent: u16,
+        /// Decoded temperature, in tenths of degree Celsius.
+        temperature_decicelsius: i16,
+        /// Raw bytes of the frame.
+        frame: [u8; 5],
+    },
+}
+
+/// Content of a frame, as 40 bits (MSB first, one bit per byte) or as 5 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub enum FrameData {
+    /// The 40 bits of the frame, each being 0 or 1.
+    Bits([u8; 40]),
+    /// The 5 bytes of the frame, the last one being the checksum.
+    Bytes([u8; 5]),
 }
 
-impl From<core::array::TryFromSliceError> for ProcessResponseError {
-    fn from(_: core::array::TryFromSliceError) -> Self {
-        ProcessResponseError::InvalidNumberOfBits
+impl From<[u8; 40]> for FrameData {
+    fn from(bits: [u8; 40]) -> Self {
+        Self::Bits(bits)
     }
 }
 
-fn process_response(bits: [u8; 40]) -> Result<(f64, f64), ProcessResponseError> {
-    let byte1 = <[u8; 8]>::try_from(&bits[0..8])?;
-    let byte2 = <[u8; 8]>::try_from(&bits[8..16])?;
-    let byte3 = <[u8; 8]>::try_from(&bits[16..24])?;
-    let byte4 = <[u8; 8]>::try_from(&bits[24..32])?;
-    let byte5 = <[u8; 8]>::try_from(&bits[32..40])?;
+impl From<[u8; 5]> for FrameData {
+    fn from(bytes: [u8; 5]) -> Self {
+        Self::Bytes(bytes)
+    }
+}
 
-    let checksum_left = [byte1, byte2, byte3, byte4]
-        .iter()
-        .map(convert_byte_to_u8)
-        .fold(0u8, |acc, x| acc.wrapping_add(x));
-    let checksum_right = convert_byte_to_u8(&byte5);
+/// Frame decoded by [`decode_frame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct Frame {
+    /// Humidity, in tenths of %.
+    pub humidity_decipercent: u16,
+    /// Temperature, in tenths of degree Celsius.
+    pub temperature_decicelsius: i16,
+    /// Checksum sent by the sensor, matching the content of the frame.
+    pub checksum: u8,
+    /// Raw bytes of the frame.
+    pub bytes: [u8; 5],
+}
 
-    if checksum_left != checksum_right {
-        return Err(ProcessResponseError::InvalidChecksumError);
+impl Frame {
+    /// Humidity and temperature of the frame, as a [`Measure`].
+    pub fn measure(&self) -> Measure {
+        Measure {
+            humidity_decipercent: self.humidity_decipercent,
+            temperature_decicelsius: self.temperature_decicelsius,
+        }
     }
+}
 
-    let mut humidity_bits = [0u8; 16];
-    humidity_bits[0..8].copy_from_slice(&byte1);
-    humidity_bits[8..16].copy_from_slice(&byte2);
+/// Decode a frame sent by a `model` sensor, captured by any mean (logic
+/// analyzer dump, UART capture, another MCU, ...), checking its checksum and
+/// the plausibility of its values.
+pub fn decode_frame(
+    data: impl Into<FrameData>,
+    model: SensorModel,
+) -> Result<Frame, ProcessResponseError> {
+    let bytes = match data.into() {
+        FrameData::Bytes(bytes) => bytes,
+        FrameData::Bits(bits) => {
+            if let Some(index) = bits.iter().position(|&bit| bit > 1) {
+                return Err(ProcessResponseError::InvalidBit { index: index as u8 });
+            }
+            convert_bits_to_bytes(&bits)
+        }
+    };
 
-    let mut humidity = 0;
-    for (idx, &bit) in humidity_bits.iter().rev().enumerate() {
-        humidity += bit as u16 * 2u16.pow(idx as u32);
+    // A line stuck low or high reads as a frame of 0s or 1s, and an all 0s
+    // frame passes the checksum.
+    if bytes.iter().all(|&byte| byte == 0) || bytes.iter().all(|&byte| byte == u8::MAX) {
+        return Err(ProcessResponseError::StuckLine { frame: bytes });
     }
 
-    let temperature_sign = if byte3[0] == 1 { -1 } else { 1 };
-    let mut temperature_bits = [0u8; 15];
-    temperature_bits[0..7].copy_from_slice(&byte3[1..8]);
-    temperature_bits[7..15].copy_from_slice(&byte4);
-    let mut temperature = 0;
-    for (idx, &bit) in temperature_bits.iter().rev().enumerate() {
-        temperature += bit as i16 * 2i16.pow(idx as u32);
+    let checksum = bytes[..4]
+        .iter()
+        .fold(0u8, |acc, &byte| acc.wrapping_add(byte));
+    if checksum != bytes[4] {
+        return Err(ProcessResponseError::InvalidChecksumError {
+            expected: checksum,
+            received: bytes[4],
+            frame: bytes,
+        });
     }
-    temperature *= temperature_sign;
 
-    Ok((humidity as f64 * 0.1, temperature as f64 * 0.1))
+    let (humidity, temperature) = model.decode([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    if !model.humidity_range_decipercent().contains(&humidity)
+        || !model.temperature_range_decicelsius().contains(&temperature)
+    {
+        return Err(ProcessResponseError::OutOfRange {
+            humidity_decipercent: humidity,
+            temperature_decicelsius: temperature,
+            frame: bytes,
+        });
+    }
+    Ok(Frame {
+        humidity_decipercent: humidity,
+        temperature_decicelsius: temperature,
+        checksum,
+        bytes,
+    })
+}
+
+fn process_response(
+    bits: [u8; 40],
+    model: SensorModel,
+) -> Result<(u16, i16), ProcessResponseError> {
+    decode_frame(bits, model)
+        .map(|frame| (frame.humidity_decipercent, frame.temperature_decicelsius))
 }
 
 fn convert_byte_to_u8(byte: &[u8; 8]) -> u8 {
@@ -64,64 +211,351 @@ fn convert_byte_to_u8(byte: &[u8; 8]) -> u8 {
     value
 }
 
-#[derive(Format)]
+fn convert_bits_to_bytes(bits: &[u8; 40]) -> [u8; 5] {
+    let mut bytes = [0u8; 5];
+    for (byte, chunk) in bytes.iter_mut().zip(bits.chunks_exact(8)) {
+        let mut bits = [0u8; 8];
+        bits.copy_from_slice(chunk);
+        *byte = convert_byte_to_u8(&bits);
+    }
+    bytes
+}
+
+#[cfg(any(test, feature = "rp2040"))]
+fn convert_bytes_to_bits(bytes: [u8; 5]) -> [u8; 40] {
+    let mut bits = [0u8; 40];
+    for (chunk, byte) in bits.chunks_exact_mut(8).zip(bytes) {
+        for (idx, bit) in chunk.iter_mut().enumerate() {
+            *bit = (byte >> (7 - idx)) & 1;
+        }
+    }
+    bits
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
 /// Possible ways a measure can fail.
 pub enum MeasureError {
-    /// A timeout occured during the measure.
-    MeasureTimeoutError,
+    /// The sensor did not acknowledge the start of the measure, it is most
+    /// likely not connected.
+    NoAcknowledgeError,
+    /// The sensor stopped answering during the handshake preceding the data.
+    HandshakeTimeoutError,
+    /// A timeout occured while reading the data bits of the measure.
+    MeasureTimeoutError {
+        /// Index of the bit that timed out, 0 being the MSB of the first byte.
+        bit: u8,
+        /// Width in µs of the low pulse preceding the bit, `None` if the line
+        /// got stuck low.
+        low_us: Option<u8>,
+        /// Width in µs of the high pulse of the previous bit, if any.
+        previous_high_us: Option<u8>,
+        /// Bytes received before the timeout, missing bits being set to 0.
+        frame: [u8; 5],
+    },
     /// The checksum of the measure does not match its content.
-    ChecksumError,
+    ChecksumError {
+        /// Checksum computed from the first 4 bytes of the frame.
+        expected: u8,
+        /// Checksum sent by the sensor.
+        received: u8,
+        /// Raw bytes received from the sensor.
+        frame: [u8; 5],
+    },
     /// Invalid measure.
     MeasureError,
+    /// The high pulses of 0 and 1 bits could not be told apart reliably.
+    AmbiguousBitsError {
+        /// Threshold and margin the high pulses were classified with.
+        classification: BitClassification,
+        /// Raw bytes as classified despite the ambiguity.
+        frame: [u8; 5],
+    },
+    /// The pin could not be read or driven.
+    PinError,
+    /// The frame only holds 0s or 1s, as read on a line stuck low or high,
+    /// which may pass the checksum.
+    StuckLineError {
+        /// Raw bytes received from the sensor.
+        frame: [u8; 5],
+    },
+    /// The decoded values are outside of the measuring range of the sensor,
+    /// despite a valid checksum.
+    OutOfRangeError {
+        /// Decoded humidity, in tenths of %.
+        humidity_decipercent: u16,
+        /// Decoded temperature, in tenths of degree Celsius.
+        temperature_decicelsius: i16,
+        /// Raw bytes received from the sensor.
+        frame: [u8; 5],
+    },
+    /// The sensor was measured less than 2s ago, or is still powering up.
+    TooSoonError,
+    /// The measure changed faster than physically plausible since the last
+    /// accepted one, see [`SpikeFilter`].
+    SpikeError {
+        /// Measure read from the sensor.
+        rejected: Measure,
+        /// Last measure accepted by the filter.
+        last: Measure,
+    },
+}
+
+impl MeasureError {
+    /// Whether the error is usually transient, so that measuring again is
+    /// likely to succeed: checksum mismatches and timeouts once the sensor
+    /// acknowledged the measure, and spikes caused by bit flips.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::HandshakeTimeoutError
+                | Self::MeasureTimeoutError { .. }
+                | Self::ChecksumError { .. }
+                | Self::SpikeError { .. }
+        )
+    }
 }
 
 impl From<ProcessResponseError> for MeasureError {
     fn from(value: ProcessResponseError) -> Self {
         match value {
-            ProcessResponseError::InvalidChecksumError => Self::ChecksumError,
-            _ => Self::MeasureError,
+            ProcessResponseError::InvalidChecksumError {
+                expected,
+                received,
+                frame,
+            } => Self::ChecksumError {
+                expected,
+                received,
+                frame,
+            },
+            ProcessResponseError::InvalidBit { .. } => Self::MeasureError,
+            ProcessResponseError::StuckLine { frame } => Self::StuckLineError { frame },
+            ProcessResponseError::OutOfRange {
+                humidity_decipercent,
+                temperature_decicelsius,
+                frame,
+            } => Self::OutOfRangeError {
+                humidity_decipercent,
+                temperature_decicelsius,
+                frame,
+            },
         }
     }
 }
 
 impl From<ReadBitsError> for MeasureError {
-    fn from(_: ReadBitsError) -> Self {
-        MeasureError::MeasureTimeoutError
+    fn from(value: ReadBitsError) -> Self {
+        match value {
+            ReadBitsError::NoAcknowledge => MeasureError::NoAcknowledgeError,
+            ReadBitsError::HandshakeTimeout => MeasureError::HandshakeTimeoutError,
+            ReadBitsError::BitTimeout {
+                bit,
+                low_us,
+                previous_high_us,
+                bits,
+            } => MeasureError::MeasureTimeoutError {
+                bit,
+                low_us,
+                previous_high_us,
+                frame: convert_bits_to_bytes(&bits),
+            },
+            ReadBitsError::AmbiguousBits {
+                classification,
+                bits,
+            } => MeasureError::AmbiguousBitsError {
+                classification,
+                frame: convert_bits_to_bytes(&bits),
+            },
+            ReadBitsError::Pin => MeasureError::PinError,
+        }
     }
 }
 
+#[cfg(feature = "rp2040")]
 #[deprecated(
     since = "0.2.0",
     note = "Has not timeout, could block forever. Use measure_once_timeout instead."
 )]
 pub async fn measure_once(pin: &mut Flex<'_>) -> Result<(f64, f64), MeasureError> {
-    let bits = measure::read_bits(pin)?;
-    let (humidity, temperature) = process_response(bits)?;
-    Ok((humidity, temperature))
+    let model = SensorModel::Am2301;
+    let bits = measure::read_bits(
+        &mut FlexOpenDrain::new(pin),
+        &mut EmbassyClock,
+        model.start_pulse_us(),
+    )?;
+    let (humidity, temperature) = process_response(bits, model)?;
+    Ok((humidity as f64 * 0.1, temperature as f64 * 0.1))
 }
 
+/// Measure decoded from the sensor, in the tenth-unit resolution of the sensor
+/// so that no floating point arithmetic is needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Format)]
 pub struct Measure {
+    /// Humidity in tenths of % between \[0, 1000\].
+    pub humidity_decipercent: u16,
+    /// Temperature in tenths of degree Celsius.
+    pub temperature_decicelsius: i16,
+}
+
+impl Measure {
     /// Humidity in % between \[0, 100\].
-    pub humidity: f64,
+    pub fn humidity_f32(&self) -> f32 {
+        self.humidity_decipercent as f32 / 10.0
+    }
+
+    /// Humidity in % between \[0, 100\].
+    pub fn humidity_f64(&self) -> f64 {
+        self.humidity_decipercent as f64 / 10.0
+    }
+
     /// Temperature in degree Celsius.
-    pub temperature: f64,
+    pub fn temperature_f32(&self) -> f32 {
+        self.temperature_decicelsius as f32 / 10.0
+    }
+
+    /// Temperature in degree Celsius.
+    pub fn temperature_f64(&self) -> f64 {
+        self.temperature_decicelsius as f64 / 10.0
+    }
 }
 
-/// Retrieve a single measure from the sensor connected in pin.
-/// Will timeout if no matching sensor is connected to the pin.
-pub async fn measure_once_timeout(pin: &mut Flex<'_>) -> Result<Measure, MeasureError> {
-    let bits = measure::read_bits_timeout(pin)?;
-    process_response(bits)
+/// Retrieve a single measure from the `model` sensor connected to an
+/// open-drain pin, using `clock` to time the protocol. Blocks for the whole
+/// transaction. Will timeout if no matching sensor is connected to the pin.
+pub fn measure_once_blocking<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    model: SensorModel,
+) -> Result<Measure, MeasureError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    measure_once_classified(pin, clock, model).map(|(measure, _)| measure)
+}
+
+/// Same as [`measure_once_blocking`], also returning how the data bits were
+/// told apart, whose confidence hints at the quality of the signal.
+pub fn measure_once_classified<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    model: SensorModel,
+) -> Result<(Measure, BitClassification), MeasureError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    read_and_decode(pin, clock, model, &mut RawFrame::new())
+}
+
+/// Same as [`measure_once_blocking`], also returning statistics on the
+/// received signal, such as the spread of the high pulses of 0 and 1 bits.
+pub fn measure_once_with_stats<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    model: SensorModel,
+) -> Result<(Measure, SignalStats), MeasureError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    let mut raw = RawFrame::new();
+    let (measure, classification) = read_and_decode(pin, clock, model, &mut raw)?;
+    Ok((measure, SignalStats::new(&raw, classification)))
+}
+
+/// Same as [`measure_once_blocking`], also capturing the timestamps of every
+/// edge of the frame, which are returned even when the measure fails.
+pub fn capture_once<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    model: SensorModel,
+) -> (RawFrame, Result<Measure, MeasureError>)
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    let mut raw = RawFrame::new();
+    let measure = read_and_decode(pin, clock, model, &mut raw).map(|(measure, _)| measure);
+    (raw, measure)
+}
+
+fn read_and_decode<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    model: SensorModel,
+    raw: &mut RawFrame,
+) -> Result<(Measure, BitClassification), MeasureError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    let (bits, classification) =
+        measure::read_bits_timeout(pin, clock, model.start_pulse_us(), raw)?;
+    Ok((measure_from_bits(bits, model)?, classification))
+}
+
+/// Retrieve a single measure from the `model` sensor connected to an
+/// open-drain pin, awaiting the line levels instead of busy-waiting on them,
+/// so that other tasks can run during the start pulse and while waiting for
+/// the sensor. `clock` timestamps the edges, `delay` times the start pulse and
+/// timeouts.
+///
+/// Edges are only caught if the executor polls the measuring task promptly,
+/// prefer [`measure_once_blocking`] when other tasks may hog the executor.
+pub async fn measure_once_async<P, C, D>(
+    pin: &mut P,
+    clock: &mut C,
+    delay: &mut D,
+    model: SensorModel,
+) -> Result<Measure, MeasureError>
+where
+    P: InputPin + OutputPin + Wait,
+    C: Clock,
+    D: DelayNs,
+{
+    let (bits, _) =
+        measure_async::read_bits_async(pin, clock, delay, model.start_pulse_us()).await?;
+    measure_from_bits(bits, model)
+}
+
+fn measure_from_bits(bits: [u8; 40], model: SensorModel) -> Result<Measure, MeasureError> {
+    process_response(bits, model)
         .map(|(humidity, temperature)| Measure {
-            humidity,
-            temperature,
+            humidity_decipercent: humidity,
+            temperature_decicelsius: temperature,
         })
         .map_err(MeasureError::from)
 }
 
+/// Retrieve a single measure from the sensor connected in pin.
+/// Will timeout if no matching sensor is connected to the pin.
+#[cfg(feature = "rp2040")]
+pub async fn measure_once_timeout(pin: &mut Flex<'_>) -> Result<Measure, MeasureError> {
+    measure_once_blocking(
+        &mut FlexOpenDrain::new(pin),
+        &mut EmbassyClock,
+        SensorModel::Am2301,
+    )
+}
+
+/// Retrieve a single measure from the sensor connected in pin, relying on GPIO
+/// interrupts so that the executor is not blocked during the measure.
+/// Will timeout if no matching sensor is connected to the pin.
+#[cfg(feature = "rp2040")]
+pub async fn measure_once_interrupt(pin: &mut Flex<'_>) -> Result<Measure, MeasureError> {
+    measure_once_async(
+        &mut FlexOpenDrain::new(pin),
+        &mut EmbassyClock,
+        &mut Delay,
+        SensorModel::Am2301,
+    )
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Simulation, Waveform, DATASHEET_FRAME};
 
     #[test]
     fn test_valid_conversion() {
@@ -134,14 +568,12 @@ mod tests {
             0, 0, 0, 0, 1, 1, 0, 1,
             1, 0, 1, 0, 0, 0, 1, 0,
         ];
-        match process_response(bits) {
+        match process_response(bits, SensorModel::Am2301) {
             Ok((humidity, temperature)) => {
-                let expected_humidity = 65.8;
-                assert!((humidity - expected_humidity).abs() < 0.01);
-                let expected_temperature = 26.9;
-                assert!((temperature - expected_temperature).abs() < 0.01);
+                assert_eq!(humidity, 658);
+                assert_eq!(temperature, 269);
             }
-            Err(_) => assert!(false),
+            Err(_) => panic!(),
         }
     }
 
@@ -156,9 +588,16 @@ mod tests {
             0, 0, 0, 0, 1, 1, 0, 1,
             1, 0, 1, 1, 0, 0, 1, 0,
         ];
-        let res = process_response(bits);
+        let res = process_response(bits, SensorModel::Am2301);
 
-        assert!(res.is_err());
+        assert!(matches!(
+            res,
+            Err(ProcessResponseError::InvalidChecksumError {
+                expected: 162,
+                received: 178,
+                frame: [2, 146, 1, 13, 178],
+            })
+        ));
     }
 
     #[test]
@@ -171,7 +610,7 @@ mod tests {
             0, 0, 0, 0, 1, 1, 0, 1,
             1, 0, 1, 0, 0, 0, 1, 0,
         ];
-        let res = process_response(bits);
+        let res = process_response(bits, SensorModel::Am2301);
 
         assert!(res.is_ok());
     }
@@ -187,15 +626,302 @@ mod tests {
             1, 0, 1, 0, 0, 0, 1, 0,
         ];
 
-        match process_response(bits) {
+        match process_response(bits, SensorModel::Am2301) {
             Ok((_, temperature)) => {
-                let expected_temperature = -26.9;
-                assert!((temperature - expected_temperature).abs() < 0.01);
+                assert_eq!(temperature, -269);
             }
-            Err(_) => assert!(false),
+            Err(_) => panic!(),
         }
     }
 
+    #[test]
+    fn fail_on_frames_of_a_stuck_line() {
+        assert!(matches!(
+            process_response([0; 40], SensorModel::Am2301),
+            Err(ProcessResponseError::StuckLine {
+                frame: [0, 0, 0, 0, 0]
+            })
+        ));
+        assert!(matches!(
+            process_response([1; 40], SensorModel::Am2301),
+            Err(ProcessResponseError::StuckLine {
+                frame: [255, 255, 255, 255, 255]
+            })
+        ));
+    }
+
+    #[test]
+    fn fail_on_values_out_of_range() {
+        // 6553.5% humidity with a valid checksum
+        let res = process_response(
+            convert_bytes_to_bits([255, 255, 0, 10, 8]),
+            SensorModel::Am2301,
+        );
+        assert!(matches!(
+            res,
+            Err(ProcessResponseError::OutOfRange {
+                humidity_decipercent: 65535,
+                temperature_decicelsius: 10,
+                ..
+            })
+        ));
+
+        // -81.0°C
+        let res = process_response(
+            convert_bytes_to_bits([1, 0, 131, 42, 174]),
+            SensorModel::Am2301,
+        );
+        assert!(matches!(
+            res,
+            Err(ProcessResponseError::OutOfRange {
+                temperature_decicelsius: -810,
+                ..
+            })
+        ));
+
+        // 95% is fine for an AM2301 but not for a DHT11
+        let bits = convert_bytes_to_bits([95, 0, 20, 0, 115]);
+        assert!(process_response(bits, SensorModel::Dht11).is_err());
+        assert!(matches!(
+            process_response(
+                convert_bytes_to_bits([3, 182, 0, 200, 129]),
+                SensorModel::Am2301
+            ),
+            Ok((950, 200))
+        ));
+    }
+
+    #[test]
+    fn measure_fails_on_all_zero_frame() {
+        let sim = Simulation::new(Waveform::from_bytes([0; 5]));
+
+        let res = measure_once_blocking(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        assert!(matches!(
+            res,
+            Err(MeasureError::StuckLineError {
+                frame: [0, 0, 0, 0, 0]
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_frame_from_bits_or_bytes() {
+        let expected = Frame {
+            humidity_decipercent: 658,
+            temperature_decicelsius: 269,
+            checksum: 162,
+            bytes: [2, 146, 1, 13, 162],
+        };
+
+        assert_eq!(
+            decode_frame(DATASHEET_FRAME, SensorModel::Am2301),
+            Ok(expected)
+        );
+        assert_eq!(
+            decode_frame(convert_bytes_to_bits(DATASHEET_FRAME), SensorModel::Am2301),
+            Ok(expected)
+        );
+        assert_eq!(expected.measure().temperature_decicelsius, 269);
+    }
+
+    #[test]
+    fn decode_frame_rejects_invalid_bits() {
+        let mut bits = convert_bytes_to_bits(DATASHEET_FRAME);
+        bits[12] = 2;
+
+        assert_eq!(
+            decode_frame(bits, SensorModel::Am2301),
+            Err(ProcessResponseError::InvalidBit { index: 12 })
+        );
+        assert!(matches!(
+            decode_frame([2, 146, 1, 13, 0], SensorModel::Am2301),
+            Err(ProcessResponseError::InvalidChecksumError { expected: 162, .. })
+        ));
+    }
+
+    #[test]
+    fn measure_from_simulated_sensor() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        match measure_once_blocking(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301) {
+            Ok(Measure {
+                humidity_decipercent,
+                temperature_decicelsius,
+            }) => {
+                assert_eq!(humidity_decipercent, 658);
+                assert_eq!(temperature_decicelsius, 269);
+            }
+            Err(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn measure_fails_on_corrupted_frame() {
+        let sim = Simulation::new(Waveform::corrupted_datasheet());
+
+        let res = measure_once_blocking(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        assert!(matches!(
+            res,
+            Err(MeasureError::ChecksumError {
+                expected: 160,
+                received: 162,
+                frame: [0, 146, 1, 13, 162],
+            })
+        ));
+    }
+
+    #[test]
+    fn measure_fails_on_truncated_frame() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.truncate(Waveform::data_bit_index(39));
+        let sim = Simulation::new(waveform);
+
+        let res = measure_once_blocking(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        assert!(matches!(
+            res,
+            Err(MeasureError::MeasureTimeoutError {
+                bit: 39,
+                frame: [2, 146, 1, 13, 162],
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn measure_async_from_simulated_sensor() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let res = embassy_futures::block_on(measure_once_async(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            SensorModel::Am2301,
+        ));
+
+        match res {
+            Ok(Measure {
+                humidity_decipercent,
+                temperature_decicelsius,
+            }) => {
+                assert_eq!(humidity_decipercent, 658);
+                assert_eq!(temperature_decicelsius, 269);
+            }
+            Err(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn measure_fails_without_sensor() {
+        let sim = Simulation::new(Waveform::empty());
+
+        let res = measure_once_blocking(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        assert!(matches!(res, Err(MeasureError::NoAcknowledgeError)));
+        assert!(sim.now_us() < 2_000);
+    }
+
+    #[test]
+    fn bytes_to_bits_round_trip() {
+        let bytes = [2, 146, 1, 13, 162];
+
+        assert_eq!(convert_bits_to_bytes(&convert_bytes_to_bits(bytes)), bytes);
+    }
+
+    #[test]
+    fn measure_from_simulated_dht11() {
+        let sim = Simulation::new(Waveform::from_bytes([45, 0, 23, 4, 72]));
+
+        let res = measure_once_blocking(&mut sim.pin(), &mut sim.clock(), SensorModel::Dht11);
+
+        assert!(matches!(
+            res,
+            Ok(Measure {
+                humidity_decipercent: 450,
+                temperature_decicelsius: 234,
+            })
+        ));
+        assert_eq!(sim.start_pulse_us(), Some(18_000));
+    }
+
+    #[test]
+    fn measure_classified_from_simulated_sensor() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let res = measure_once_classified(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        assert!(matches!(
+            res,
+            Ok((
+                _,
+                BitClassification {
+                    confidence: 80..=100,
+                    ..
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn measure_with_stats_from_simulated_sensor() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let res = measure_once_with_stats(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        let Ok((_, stats)) = res else {
+            panic!();
+        };
+        assert_eq!(stats.zeros.map(|zeros| zeros.count), Some(29));
+        assert_eq!(stats.ones.map(|ones| ones.count), Some(11));
+        assert!(matches!(
+            stats.zeros,
+            Some(PulseStats {
+                mean_us: 24..=28,
+                ..
+            })
+        ));
+        assert!(matches!(
+            stats.ones,
+            Some(PulseStats {
+                mean_us: 68..=72,
+                ..
+            })
+        ));
+        assert!(stats.handshake_low_us.abs_diff(80) <= 2);
+        assert!(stats.handshake_high_us.abs_diff(80) <= 2);
+        assert!(stats.polls > 0);
+    }
+
+    #[test]
+    fn capture_frame_of_failed_measure() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.truncate(Waveform::data_bit_index(10));
+        let sim = Simulation::new(waveform);
+
+        let (raw, res) = capture_once(&mut sim.pin(), &mut sim.clock(), SensorModel::Am2301);
+
+        assert!(matches!(
+            res,
+            Err(MeasureError::MeasureTimeoutError { bit: 10, .. })
+        ));
+        assert_eq!(raw.edges_us().len(), 5 + 2 * 10 + 1);
+    }
+
+    #[test]
+    fn measure_conversions_to_float() {
+        let measure = Measure {
+            humidity_decipercent: 658,
+            temperature_decicelsius: -269,
+        };
+
+        assert!((measure.humidity_f32() - 65.8).abs() < 0.01);
+        assert!((measure.humidity_f64() - 65.8).abs() < 0.01);
+        assert!((measure.temperature_f32() + 26.9).abs() < 0.01);
+        assert!((measure.temperature_f64() + 26.9).abs() < 0.01);
+    }
+
     #[test]
     fn u8_addition_overflow() {
         let num1 = 250u8;
@@ -0,0 +1,197 @@
+//! Backend for Linux boards (Raspberry Pi, ...), driving the sensor through the
+//! GPIO character device of the kernel.
+//!
+//! Linux is not a real-time OS: reading a line costs a system call, and the
+//! measuring thread may be preempted in the middle of a frame, making
+//! timeouts and checksum errors more frequent than on a microcontroller.
+
+use std::time::{Duration, Instant};
+
+use embedded_hal::digital::{self, ErrorType, InputPin, OutputPin};
+use gpio_cdev::{Line, LineHandle, LineRequestFlags};
+
+use crate::clock::Clock;
+
+/// Label of the lines requested by the driver, shown by `gpioinfo`.
+const CONSUMER: &str = "am2301";
+
+/// Error raised by the GPIO character device.
+#[derive(Debug)]
+pub struct CdevError(pub gpio_cdev::Error);
+
+impl digital::Error for CdevError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+impl From<gpio_cdev::Error> for CdevError {
+    fn from(value: gpio_cdev::Error) -> Self {
+        Self(value)
+    }
+}
+
+/// Open-drain pin over a GPIO line, requested once as an open-drain output:
+/// driving it high releases it to the pull-up resistor, and reading it gives
+/// the actual level of the line.
+pub struct CdevOpenDrain {
+    line: Line,
+    handle: LineHandle,
+}
+
+impl CdevOpenDrain {
+    /// Request `line`, released.
+    pub fn new(line: Line) -> Result<Self, CdevError> {
+        let flags = LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN;
+        let handle = line.request(flags, 1, CONSUMER)?;
+        Ok(Self { line, handle })
+    }
+
+    /// Release the line.
+    pub fn free(self) -> Line {
+        let Self { line, handle } = self;
+        drop(handle);
+        line
+    }
+}
+
+impl ErrorType for CdevOpenDrain {
+    type Error = CdevError;
+}
+
+impl InputPin for CdevOpenDrain {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.handle.get_value()? == 1)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+impl OutputPin for CdevOpenDrain {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.handle.set_value(0)?;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.handle.set_value(1)?;
+        Ok(())
+    }
+}
+
+/// [`Clock`] backed by the monotonic clock of the OS, busy-waiting for the
+/// short delays of the protocol.
+#[derive(Clone, Copy, Debug)]
+pub struct StdClock {
+    origin: Instant,
+}
+
+impl StdClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for StdClock {
+    fn now_micros(&mut self) -> u64 {
+        self.origin.elapsed().as_micros() as u64
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        let deadline = self.origin.elapsed() + Duration::from_micros(us as u64);
+        while self.origin.elapsed() < deadline {}
+    }
+}
+
+/// Delay sleeping the current thread, to use with the [`crate::Am2301`]
+/// driver along with [`StdClock`].
+///
+/// The async implementation sleeps the thread too, blocking any executor
+/// running on it: it is only suitable for driving the futures with a
+/// `block_on`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdDelay;
+
+impl embedded_hal::delay::DelayNs for StdDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        std::thread::sleep(Duration::from_nanos(ns as u64));
+    }
+}
+
+impl embedded_hal_async::delay::DelayNs for StdDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        std::thread::sleep(Duration::from_nanos(ns as u64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use gpio_cdev::Chip;
+
+    use super::*;
+    use crate::{measure_once_blocking, MeasureError, SensorModel};
+
+    #[test]
+    fn clock_busy_waits_for_delays() {
+        let mut clock = StdClock::new();
+
+        let start = clock.now_micros();
+        clock.delay_us(200);
+
+        assert!(clock.now_micros() - start >= 200);
+    }
+
+    #[test]
+    fn delay_sleeps_at_least_the_given_duration() {
+        let start = Instant::now();
+
+        block_on(embedded_hal_async::delay::DelayNs::delay_ms(
+            &mut StdDelay,
+            5,
+        ));
+
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+
+    /// Runs against a `gpio-sim` chip whose line 0 is pulled up, given by
+    /// `AM2301_GPIO_SIM_CHIP`, e.g. `/dev/gpiochip2`.
+    #[test]
+    #[ignore = "needs a gpio-sim chip"]
+    fn drive_and_release_a_simulated_line() {
+        let path = std::env::var("AM2301_GPIO_SIM_CHIP").unwrap();
+        let line = Chip::new(path).unwrap().get_line(0).unwrap();
+        let mut pin = CdevOpenDrain::new(line).unwrap();
+
+        assert!(pin.is_high().unwrap());
+        pin.set_low().unwrap();
+        assert!(pin.is_low().unwrap());
+        pin.set_high().unwrap();
+        assert!(pin.is_high().unwrap());
+    }
+
+    /// Runs a whole measure against the same `gpio-sim` line, with no sensor
+    /// to answer the start pulse.
+    #[test]
+    #[ignore = "needs a gpio-sim chip"]
+    fn measure_without_sensor_on_a_simulated_line() {
+        let path = std::env::var("AM2301_GPIO_SIM_CHIP").unwrap();
+        let line = Chip::new(path).unwrap().get_line(0).unwrap();
+        let mut pin = CdevOpenDrain::new(line).unwrap();
+
+        let res = measure_once_blocking(&mut pin, &mut StdClock::new(), SensorModel::Am2301);
+
+        assert!(matches!(res, Err(MeasureError::NoAcknowledgeError)));
+        assert!(pin.is_high().unwrap());
+    }
+}
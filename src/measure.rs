@@ -1,108 +1,467 @@
-use embassy_rp::gpio::Flex;
-use embassy_time::{block_for, Duration, Instant};
+use embedded_hal::digital::{InputPin, OutputPin};
 
-const TIMEOUT_DURATION: Duration = Duration::from_micros(100);
+use crate::classify::{classify_pulses, reference_bits, BitClassification};
+use crate::clock::Clock;
+use crate::raw::RawFrame;
 
-fn trigger_measure(pin: &mut Flex<'_>) {
-    pin.set_high();
-    pin.set_as_output();
+/// Maximum time for the sensor to pull the line low after the start pulse.
+pub(crate) const ACKNOWLEDGE_TIMEOUT_US: u64 = 200;
+/// Maximum duration of each of the 80us low and high handshake pulses.
+pub(crate) const HANDSHAKE_TIMEOUT_US: u64 = 100;
+/// Maximum duration of each level while transmitting a data bit.
+pub(crate) const BIT_TIMEOUT_US: u64 = 100;
 
-    // Set to low for 1ms
-    pin.set_low();
-    block_for(Duration::from_millis(1));
-    pin.set_high();
+fn trigger_measure<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    start_pulse_us: u32,
+    raw: &mut RawFrame,
+) -> Result<(), ReadBitsError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    pin.set_high().map_err(pin_error)?;
 
-    pin.set_as_input();
+    // Set to low for the start pulse, 1ms for most sensors
+    raw.start(clock.now_micros());
+    pin.set_low().map_err(pin_error)?;
+    clock.delay_us(start_pulse_us);
+
+    // Release the line so that the sensor can drive it
+    pin.set_high().map_err(pin_error)?;
+    raw.push(clock.now_micros());
+    Ok(())
 }
 
-fn wait_for_falling_edge(pin: &mut Flex<'_>) -> u8 {
-    let start = Instant::now();
-    let mut pin_is_low = pin.is_low();
-    while !pin_is_low {
-        pin_is_low = pin.is_low();
-        block_for(Duration::from_micros(1));
-    }
-    start.elapsed().as_micros() as u8
+/// Width in µs of a pulse between two timestamps, saturating at 255µs.
+pub(crate) fn width_us(from_us: u64, to_us: u64) -> u8 {
+    (to_us - from_us).min(u8::MAX as u64) as u8
 }
 
-fn wait_for_rising_edge(pin: &mut Flex<'_>) -> u8 {
-    let start = Instant::now();
-    let mut pin_is_high = pin.is_high();
-    while !pin_is_high {
-        pin_is_high = pin.is_high();
+#[cfg(feature = "rp2040")]
+fn wait_for_falling_edge<P: InputPin, C: Clock>(
+    pin: &mut P,
+    clock: &mut C,
+) -> Result<u64, ReadBitsError> {
+    while !pin.is_low().map_err(pin_error)? {
+        clock.delay_us(1);
     }
-    start.elapsed().as_micros() as u8
+    Ok(clock.now_micros())
 }
 
-fn wait_for_falling_edge_timeout(pin: &mut Flex<'_>) -> Option<u8> {
-    let start = Instant::now();
-    while pin.is_high() {
-        if start.elapsed() > TIMEOUT_DURATION {
-            return None;
+#[cfg(feature = "rp2040")]
+fn wait_for_rising_edge<P: InputPin, C: Clock>(
+    pin: &mut P,
+    clock: &mut C,
+) -> Result<u64, ReadBitsError> {
+    while !pin.is_high().map_err(pin_error)? {}
+    Ok(clock.now_micros())
+}
+
+/// Returns the timestamp of the edge, after recording it in `raw`.
+fn wait_for_falling_edge_timeout<P: InputPin, C: Clock>(
+    pin: &mut P,
+    clock: &mut C,
+    raw: &mut RawFrame,
+    timeout_us: u64,
+    on_timeout: impl FnOnce() -> ReadBitsError,
+) -> Result<u64, ReadBitsError> {
+    let start = clock.now_micros();
+    while {
+        raw.count_poll();
+        pin.is_high().map_err(pin_error)?
+    } {
+        if clock.now_micros() - start > timeout_us {
+            return Err(on_timeout());
         }
-        block_for(Duration::from_micros(1));
+        clock.delay_us(1);
     }
-    Some(start.elapsed().as_micros() as u8)
+    let edge = clock.now_micros();
+    raw.push(edge);
+    Ok(edge)
 }
 
-fn wait_for_rising_edge_timeout(pin: &mut Flex<'_>) -> Option<u8> {
-    let start = Instant::now();
-    while pin.is_low() {
-        if start.elapsed() > TIMEOUT_DURATION {
-            return None;
+/// Returns the timestamp of the edge, after recording it in `raw`.
+fn wait_for_rising_edge_timeout<P: InputPin, C: Clock>(
+    pin: &mut P,
+    clock: &mut C,
+    raw: &mut RawFrame,
+    timeout_us: u64,
+    on_timeout: impl FnOnce() -> ReadBitsError,
+) -> Result<u64, ReadBitsError> {
+    let start = clock.now_micros();
+    while {
+        raw.count_poll();
+        pin.is_low().map_err(pin_error)?
+    } {
+        if clock.now_micros() - start > timeout_us {
+            return Err(on_timeout());
         }
         // Not blocking here, as it tends to create a lot of timeout
-        // block_for(Duration::from_micros(1));
+        // clock.delay_us(1);
     }
-    Some(start.elapsed().as_micros() as u8)
+    let edge = clock.now_micros();
+    raw.push(edge);
+    Ok(edge)
 }
 
-fn skip_start_of_measure(pin: &mut Flex<'_>) {
+/// Returns the timestamp of the end of the handshake, and the width of its
+/// high pulse.
+fn skip_start_of_measure<P: InputPin, C: Clock>(
+    pin: &mut P,
+    clock: &mut C,
+    raw: &mut RawFrame,
+) -> Result<(u64, u8), ReadBitsError> {
     // Measure starts with a falling edge, a rising edge, and a final falling edge.
-    wait_for_falling_edge(pin);
-    wait_for_rising_edge(pin);
-    wait_for_falling_edge(pin);
+    wait_for_falling_edge_timeout(pin, clock, raw, ACKNOWLEDGE_TIMEOUT_US, || {
+        ReadBitsError::NoAcknowledge
+    })?;
+    let rise = wait_for_rising_edge_timeout(pin, clock, raw, HANDSHAKE_TIMEOUT_US, || {
+        ReadBitsError::HandshakeTimeout
+    })?;
+    let fall = wait_for_falling_edge_timeout(pin, clock, raw, HANDSHAKE_TIMEOUT_US, || {
+        ReadBitsError::HandshakeTimeout
+    })?;
+    Ok((fall, width_us(rise, fall)))
 }
 
 pub enum ReadBitsError {
-    TimeoutErr,
+    /// The sensor did not pull the line low after the start pulse.
+    NoAcknowledge,
+    /// The sensor stopped in the middle of the 80us low/high handshake.
+    HandshakeTimeout,
+    /// The sensor stopped while transmitting a data bit.
+    BitTimeout {
+        /// Index of the bit being read.
+        bit: u8,
+        /// Width of the low pulse preceding the bit, if it ended.
+        low_us: Option<u8>,
+        /// Width of the high pulse of the previous bit, if any.
+        previous_high_us: Option<u8>,
+        /// Bits read before the timeout.
+        bits: [u8; 40],
+    },
+    /// High pulses of 0 and 1 bits could not be told apart.
+    AmbiguousBits {
+        /// Threshold and margin the high pulses were classified with.
+        classification: BitClassification,
+        /// Bits as classified despite the ambiguity.
+        bits: [u8; 40],
+    },
+    Pin,
 }
 
-pub fn read_bits(pin: &mut Flex<'_>) -> Result<[u8; 40], ReadBitsError> {
-    let mut measures = [0u8; 40];
-    trigger_measure(pin);
-    pin.set_as_input();
+pub(crate) fn pin_error<E>(_: E) -> ReadBitsError {
+    ReadBitsError::Pin
+}
 
-    skip_start_of_measure(pin);
+pub(crate) fn classify_bits(
+    high_us: &[u8; 40],
+    handshake_high_us: u8,
+) -> Result<([u8; 40], BitClassification), ReadBitsError> {
+    classify_pulses(high_us, handshake_high_us).map_err(|classification| {
+        ReadBitsError::AmbiguousBits {
+            classification,
+            bits: reference_bits(high_us, handshake_high_us),
+        }
+    })
+}
 
-    for measure in measures.iter_mut() {
-        wait_for_rising_edge(pin);
-        let delay = wait_for_falling_edge(pin);
-        *measure = match delay {
-            d if d > 50 => 1,
-            _ => 0,
-        };
+#[cfg(feature = "rp2040")]
+pub fn read_bits<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    start_pulse_us: u32,
+) -> Result<[u8; 40], ReadBitsError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    let mut high_us = [0u8; 40];
+    let mut raw = RawFrame::new();
+    trigger_measure(pin, clock, start_pulse_us, &mut raw)?;
+
+    let (_, handshake_high_us) = skip_start_of_measure(pin, clock, &mut raw)?;
+
+    for pulse in high_us.iter_mut() {
+        let rise = wait_for_rising_edge(pin, clock)?;
+        *pulse = width_us(rise, wait_for_falling_edge(pin, clock)?);
     }
 
-    Ok(measures)
+    classify_bits(&high_us, handshake_high_us).map(|(bits, _)| bits)
 }
 
-pub fn read_bits_timeout(pin: &mut Flex<'_>) -> Result<[u8; 40], ReadBitsError> {
-    let mut measures = [0u8; 40];
+/// Read the 40 bits of a frame, recording each edge of the frame in `raw`.
+pub fn read_bits_timeout<P, C>(
+    pin: &mut P,
+    clock: &mut C,
+    start_pulse_us: u32,
+    raw: &mut RawFrame,
+) -> Result<([u8; 40], BitClassification), ReadBitsError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+{
+    let mut high_us = [0u8; 40];
 
-    trigger_measure(pin);
-    pin.set_as_input();
+    trigger_measure(pin, clock, start_pulse_us, raw)?;
 
-    skip_start_of_measure(pin);
+    let (mut fall, handshake_high_us) = skip_start_of_measure(pin, clock, raw)?;
 
-    for measure in measures.iter_mut() {
-        wait_for_rising_edge_timeout(pin).ok_or(ReadBitsError::TimeoutErr)?;
-        let delay = wait_for_falling_edge_timeout(pin).ok_or(ReadBitsError::TimeoutErr)?;
-        *measure = match delay {
-            d if d > 50 => 1,
-            _ => 0,
+    for bit in 0..high_us.len() {
+        let timeout = |low_us| ReadBitsError::BitTimeout {
+            bit: bit as u8,
+            low_us,
+            previous_high_us: bit.checked_sub(1).map(|idx| high_us[idx]),
+            bits: reference_bits(&high_us[..bit], handshake_high_us),
         };
+        let rise = wait_for_rising_edge_timeout(pin, clock, raw, BIT_TIMEOUT_US, || timeout(None))?;
+        let low_us = width_us(fall, rise);
+        fall = wait_for_falling_edge_timeout(pin, clock, raw, BIT_TIMEOUT_US, || {
+            timeout(Some(low_us))
+        })?;
+        high_us[bit] = width_us(rise, fall);
+    }
+
+    classify_bits(&high_us, handshake_high_us)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Segment, Simulation, Waveform, DATASHEET_BITS, DATASHEET_FRAME};
+
+    const START_PULSE_US: u32 = 1_000;
+
+    #[test]
+    fn read_bits_of_a_valid_frame() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Ok((bits, _)) if bits == DATASHEET_BITS));
+    }
+
+    #[test]
+    fn record_every_edge_of_the_frame() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut raw = RawFrame::new();
+
+        let _ = read_bits_timeout(&mut sim.pin(), &mut sim.clock(), START_PULSE_US, &mut raw);
+
+        assert!(raw.is_complete());
+        let mut pulses = raw.pulses();
+        assert!(matches!(pulses.next(), Some(p) if !p.high && p.duration_us == 1_000));
+        assert!(matches!(pulses.next(), Some(p) if p.high && p.duration_us.abs_diff(30) <= 2));
+        assert!(matches!(pulses.next(), Some(p) if !p.high && p.duration_us.abs_diff(80) <= 2));
+        assert!(matches!(pulses.next(), Some(p) if p.high && p.duration_us.abs_diff(80) <= 2));
+        let highs = pulses.skip(1).step_by(2).map(|p| p.duration_us);
+        assert!(highs
+            .zip(DATASHEET_BITS)
+            .all(|(high, bit)| high.abs_diff(if bit == 1 { 70 } else { 26 }) <= 2));
     }
 
-    Ok(measures)
+    #[test]
+    fn pulse_widths_saturate() {
+        assert_eq!(width_us(1_000, 1_070), 70);
+        assert_eq!(width_us(1_000, 1_300), u8::MAX);
+    }
+
+    #[test]
+    fn start_pulse_lasts_one_millisecond() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let _ = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert_eq!(sim.start_pulse_us(), Some(1_000));
+    }
+
+    #[test]
+    fn classify_bits_around_the_threshold() {
+        let mut waveform = Waveform::from_bytes([0; 5]);
+        waveform.set(Waveform::data_bit_index(0), Segment::high(45));
+        waveform.set(Waveform::data_bit_index(1), Segment::high(55));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Ok((bits, _)) if bits[0] == 0 && bits[1] == 1));
+    }
+
+    #[test]
+    fn ambiguous_bits_on_overlapping_pulses() {
+        let sim = Simulation::new(Waveform::from_bytes_with_highs(DATASHEET_FRAME, 48, 52));
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Err(ReadBitsError::AmbiguousBits { .. })));
+    }
+
+    #[test]
+    fn classify_long_cable_pulses() {
+        // A slow pull-up delays every rising edge by ~22us, shortening high
+        // pulses below the nominal 50us threshold.
+        let mut waveform = Waveform::from_bytes_with_highs(DATASHEET_FRAME, 8, 48);
+        waveform.set(2, Segment::high(58));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Ok((bits, _)) if bits == DATASHEET_BITS));
+    }
+
+    #[test]
+    fn timeout_on_truncated_frame() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.truncate(Waveform::data_bit_index(20));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(
+            bits,
+            Err(ReadBitsError::BitTimeout {
+                bit: 20,
+                low_us: Some(_),
+                previous_high_us: Some(_),
+                bits,
+            }) if bits[..20] == DATASHEET_BITS[..20] && bits[20..] == [0; 20]
+        ));
+        assert!(sim.now_us() < 10_000);
+    }
+
+    #[test]
+    fn timeout_on_stalled_bit() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.set(Waveform::data_bit_index(10) - 1, Segment::low(500));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(
+            bits,
+            Err(ReadBitsError::BitTimeout {
+                bit: 10,
+                low_us: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn no_acknowledge_without_sensor() {
+        let sim = Simulation::new(Waveform::empty());
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Err(ReadBitsError::NoAcknowledge)));
+    }
+
+    #[test]
+    fn no_acknowledge_on_line_stuck_high_after_release() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.set(0, Segment::high(1_000));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Err(ReadBitsError::NoAcknowledge)));
+    }
+
+    #[test]
+    fn handshake_timeout_on_stuck_low_line() {
+        let mut waveform = Waveform::empty();
+        waveform.push(Segment::high(30)).push(Segment::low(100_000));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Err(ReadBitsError::HandshakeTimeout)));
+    }
+
+    #[test]
+    fn handshake_timeout_on_stalled_high_pulse() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.set(2, Segment::high(500));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Err(ReadBitsError::HandshakeTimeout)));
+    }
+
+    #[test]
+    fn glitch_shifts_the_following_bits() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        let idx = Waveform::data_bit_index(6);
+        waveform.set(idx, Segment::high(30));
+        waveform.insert(idx + 1, Segment::low(2));
+        waveform.insert(idx + 2, Segment::high(38));
+        let sim = Simulation::new(waveform);
+
+        let bits = read_bits_timeout(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            START_PULSE_US,
+            &mut RawFrame::new(),
+        );
+
+        assert!(matches!(bits, Ok((bits, _)) if bits != DATASHEET_BITS));
+    }
 }
@@ -0,0 +1,204 @@
+use embassy_futures::select::{select, Either};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+
+use crate::classify::{reference_bits, BitClassification};
+use crate::clock::Clock;
+use crate::measure::{
+    classify_bits, pin_error, width_us, ReadBitsError, ACKNOWLEDGE_TIMEOUT_US, BIT_TIMEOUT_US,
+    HANDSHAKE_TIMEOUT_US,
+};
+
+async fn trigger_measure<P, D>(
+    pin: &mut P,
+    delay: &mut D,
+    start_pulse_us: u32,
+) -> Result<(), ReadBitsError>
+where
+    P: InputPin + OutputPin,
+    D: DelayNs,
+{
+    pin.set_high().map_err(pin_error)?;
+
+    // Set to low for the start pulse, yielding to other tasks in the meantime
+    pin.set_low().map_err(pin_error)?;
+    delay.delay_us(start_pulse_us).await;
+
+    // Release the line so that the sensor can drive it
+    pin.set_high().map_err(pin_error)
+}
+
+async fn wait_for_falling_edge_timeout<P: Wait, C: Clock, D: DelayNs>(
+    pin: &mut P,
+    clock: &mut C,
+    delay: &mut D,
+    timeout_us: u64,
+    on_timeout: impl FnOnce() -> ReadBitsError,
+) -> Result<u8, ReadBitsError> {
+    let start = clock.now_micros();
+    match select(pin.wait_for_low(), delay.delay_us(timeout_us as u32)).await {
+        Either::First(res) => res.map_err(pin_error)?,
+        Either::Second(()) => return Err(on_timeout()),
+    }
+    Ok(width_us(start, clock.now_micros()))
+}
+
+async fn wait_for_rising_edge_timeout<P: Wait, C: Clock, D: DelayNs>(
+    pin: &mut P,
+    clock: &mut C,
+    delay: &mut D,
+    timeout_us: u64,
+    on_timeout: impl FnOnce() -> ReadBitsError,
+) -> Result<u8, ReadBitsError> {
+    let start = clock.now_micros();
+    match select(pin.wait_for_high(), delay.delay_us(timeout_us as u32)).await {
+        Either::First(res) => res.map_err(pin_error)?,
+        Either::Second(()) => return Err(on_timeout()),
+    }
+    Ok(width_us(start, clock.now_micros()))
+}
+
+/// Returns the width of the handshake high pulse.
+async fn skip_start_of_measure<P: Wait, C: Clock, D: DelayNs>(
+    pin: &mut P,
+    clock: &mut C,
+    delay: &mut D,
+) -> Result<u8, ReadBitsError> {
+    // Measure starts with a falling edge, a rising edge, and a final falling edge.
+    wait_for_falling_edge_timeout(pin, clock, delay, ACKNOWLEDGE_TIMEOUT_US, || {
+        ReadBitsError::NoAcknowledge
+    })
+    .await?;
+    wait_for_rising_edge_timeout(pin, clock, delay, HANDSHAKE_TIMEOUT_US, || {
+        ReadBitsError::HandshakeTimeout
+    })
+    .await?;
+    wait_for_falling_edge_timeout(pin, clock, delay, HANDSHAKE_TIMEOUT_US, || {
+        ReadBitsError::HandshakeTimeout
+    })
+    .await
+}
+
+/// Same as [`crate::measure::read_bits_timeout`], but awaiting the pin levels
+/// instead of polling them so that the executor can run other tasks.
+pub async fn read_bits_async<P, C, D>(
+    pin: &mut P,
+    clock: &mut C,
+    delay: &mut D,
+    start_pulse_us: u32,
+) -> Result<([u8; 40], BitClassification), ReadBitsError>
+where
+    P: InputPin + OutputPin + Wait,
+    C: Clock,
+    D: DelayNs,
+{
+    let mut high_us = [0u8; 40];
+
+    trigger_measure(pin, delay, start_pulse_us).await?;
+
+    let handshake_high_us = skip_start_of_measure(pin, clock, delay).await?;
+
+    for bit in 0..high_us.len() {
+        let timeout = |low_us| ReadBitsError::BitTimeout {
+            bit: bit as u8,
+            low_us,
+            previous_high_us: bit.checked_sub(1).map(|idx| high_us[idx]),
+            bits: reference_bits(&high_us[..bit], handshake_high_us),
+        };
+        let low_us =
+            wait_for_rising_edge_timeout(pin, clock, delay, BIT_TIMEOUT_US, || timeout(None))
+                .await?;
+        high_us[bit] = wait_for_falling_edge_timeout(pin, clock, delay, BIT_TIMEOUT_US, || {
+            timeout(Some(low_us))
+        })
+        .await?;
+    }
+
+    classify_bits(&high_us, handshake_high_us)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::pin::pin;
+    use core::task::Poll;
+
+    use embassy_futures::block_on;
+    use embassy_futures::poll_once;
+
+    use super::*;
+    use crate::mock::{Simulation, Waveform, DATASHEET_BITS, DATASHEET_FRAME};
+
+    const START_PULSE_US: u32 = 1_000;
+
+    #[test]
+    fn read_bits_of_a_valid_frame() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let bits = block_on(read_bits_async(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            START_PULSE_US,
+        ));
+
+        assert!(matches!(bits, Ok((bits, _)) if bits == DATASHEET_BITS));
+        assert_eq!(sim.start_pulse_us(), Some(1_000));
+    }
+
+    #[test]
+    fn yields_during_start_pulse() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let (mut pin, mut clock, mut delay) = (sim.pin(), sim.clock(), sim.clock());
+        let mut read = pin!(read_bits_async(
+            &mut pin,
+            &mut clock,
+            &mut delay,
+            START_PULSE_US
+        ));
+
+        assert!(poll_once(read.as_mut()).is_pending());
+        assert!(sim.start_pulse_us().is_none());
+        assert!(sim.now_us() < 1_000);
+
+        let bits = loop {
+            if let Poll::Ready(bits) = poll_once(read.as_mut()) {
+                break bits;
+            }
+        };
+        assert!(matches!(bits, Ok((bits, _)) if bits == DATASHEET_BITS));
+    }
+
+    #[test]
+    fn no_acknowledge_without_sensor() {
+        let sim = Simulation::new(Waveform::empty());
+
+        let bits = block_on(read_bits_async(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            START_PULSE_US,
+        ));
+
+        assert!(matches!(bits, Err(ReadBitsError::NoAcknowledge)));
+    }
+
+    #[test]
+    fn timeout_on_truncated_frame() {
+        let mut waveform = Waveform::from_bytes(DATASHEET_FRAME);
+        waveform.truncate(Waveform::data_bit_index(20));
+        let sim = Simulation::new(waveform);
+
+        let bits = block_on(read_bits_async(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            START_PULSE_US,
+        ));
+
+        assert!(matches!(
+            bits,
+            Err(ReadBitsError::BitTimeout { bit: 20, .. })
+        ));
+    }
+}
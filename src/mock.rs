@@ -0,0 +1,353 @@
+//! Host-side simulation of an AM2301 sensor, used to exercise the whole read
+//! path under `cargo test`.
+//!
+//! A [`Simulation`] replays a scripted [`Waveform`] once the host releases the
+//! line after its start pulse. Time only moves forward when the code under test
+//! polls the pin or waits on the clock, which makes runs deterministic. Async
+//! waits on the pin never advance time by themselves, they rely on a concurrent
+//! delay to do so.
+
+use core::cell::Cell;
+use core::convert::Infallible;
+
+use embassy_futures::yield_now;
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+
+use crate::clock::Clock;
+use crate::Measure;
+
+/// Minimum duration the host must hold the line low for the sensor to answer.
+const MIN_START_PULSE_US: u64 = 500;
+/// Time spent by the simulated MCU for each pin read.
+const POLL_COST_US: u64 = 1;
+
+/// Delays are simulated µs by µs only over their last millisecond.
+const LONG_DELAY_US: u64 = 1_000;
+
+const MAX_SEGMENTS: usize = 128;
+
+// 00000010 10010010 00000001 00001101 10100010 example from datasheet
+pub const DATASHEET_FRAME: [u8; 5] = [
+    0b0000_0010,
+    0b1001_0010,
+    0b0000_0001,
+    0b0000_1101,
+    0b1010_0010,
+];
+
+/// Bits of [`DATASHEET_FRAME`], MSB of the first byte first.
+#[rustfmt::skip]
+pub const DATASHEET_BITS: [u8; 40] = [
+    0, 0, 0, 0, 0, 0, 1, 0,
+    1, 0, 0, 1, 0, 0, 1, 0,
+    0, 0, 0, 0, 0, 0, 0, 1,
+    0, 0, 0, 0, 1, 1, 0, 1,
+    1, 0, 1, 0, 0, 0, 1, 0,
+];
+
+pub const fn measure(humidity_decipercent: u16, temperature_decicelsius: i16) -> Measure {
+    Measure {
+        humidity_decipercent,
+        temperature_decicelsius,
+    }
+}
+
+/// Line level held for a given duration, in microseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub high: bool,
+    pub duration_us: u32,
+}
+
+impl Segment {
+    pub const fn low(duration_us: u32) -> Self {
+        Self {
+            high: false,
+            duration_us,
+        }
+    }
+
+    pub const fn high(duration_us: u32) -> Self {
+        Self {
+            high: true,
+            duration_us,
+        }
+    }
+}
+
+/// Sequence of levels driven by the sensor once the host releases the line.
+/// The line is pulled up (high) after the last segment.
+#[derive(Clone, Copy, Debug)]
+pub struct Waveform {
+    segments: [Segment; MAX_SEGMENTS],
+    len: usize,
+}
+
+impl Waveform {
+    pub const fn empty() -> Self {
+        Self {
+            segments: [Segment::high(0); MAX_SEGMENTS],
+            len: 0,
+        }
+    }
+
+    /// Waveform of a well-behaved sensor sending the given 5 bytes, using the
+    /// nominal timings of the datasheet.
+    pub fn from_bytes(bytes: [u8; 5]) -> Self {
+        Self::from_bytes_with_highs(bytes, 26, 70)
+    }
+
+    /// Waveform of [`DATASHEET_FRAME`] with bit 6 read as 0, which breaks the
+    /// checksum.
+    pub fn corrupted_datasheet() -> Self {
+        let mut waveform = Self::from_bytes(DATASHEET_FRAME);
+        waveform.set(Self::data_bit_index(6), Segment::high(26));
+        waveform
+    }
+
+    /// Waveform sending the given 5 bytes, with custom high pulses for 0 and 1
+    /// bits.
+    pub fn from_bytes_with_highs(bytes: [u8; 5], zero_us: u32, one_us: u32) -> Self {
+        let mut waveform = Self::empty();
+        // Host release, then sensor acknowledge.
+        waveform.push(Segment::high(30));
+        waveform.push(Segment::low(80));
+        waveform.push(Segment::high(80));
+        for byte in bytes {
+            for idx in (0..8).rev() {
+                let high = if (byte >> idx) & 1 == 1 {
+                    one_us
+                } else {
+                    zero_us
+                };
+                waveform.push(Segment::low(50));
+                waveform.push(Segment::high(high));
+            }
+        }
+        // End of frame, then the sensor releases the line.
+        waveform.push(Segment::low(50));
+        waveform
+    }
+
+    pub fn push(&mut self, segment: Segment) -> &mut Self {
+        self.segments[self.len] = segment;
+        self.len += 1;
+        self
+    }
+
+    /// Keep only the first `len` segments.
+    pub fn truncate(&mut self, len: usize) -> &mut Self {
+        self.len = self.len.min(len);
+        self
+    }
+
+    /// Replace the segment at `idx`.
+    pub fn set(&mut self, idx: usize, segment: Segment) -> &mut Self {
+        self.segments[idx] = segment;
+        self
+    }
+
+    /// Insert a segment at `idx`, shifting the following ones.
+    pub fn insert(&mut self, idx: usize, segment: Segment) -> &mut Self {
+        self.segments.copy_within(idx..self.len, idx + 1);
+        self.segments[idx] = segment;
+        self.len += 1;
+        self
+    }
+
+    /// Index of the high segment carrying data bit `bit` (0 being the MSB of the
+    /// first byte) in a waveform built by [`Waveform::from_bytes`].
+    pub const fn data_bit_index(bit: usize) -> usize {
+        3 + 2 * bit + 1
+    }
+
+    fn level_at(&self, elapsed_us: u64) -> bool {
+        let mut end = 0u64;
+        for segment in &self.segments[..self.len] {
+            end += segment.duration_us as u64;
+            if elapsed_us < end {
+                return segment.high;
+            }
+        }
+        true
+    }
+}
+
+/// Shared state between a [`MockPin`] and a [`MockClock`].
+pub struct Simulation {
+    waveform: Waveform,
+    first_responses: Option<(Waveform, u32)>,
+    triggers: Cell<u32>,
+    now_us: Cell<u64>,
+    host_low_since: Cell<Option<u64>>,
+    response_start: Cell<Option<u64>>,
+    start_pulse_us: Cell<Option<u64>>,
+}
+
+impl Simulation {
+    pub fn new(waveform: Waveform) -> Self {
+        Self {
+            waveform,
+            first_responses: None,
+            triggers: Cell::new(0),
+            now_us: Cell::new(0),
+            host_low_since: Cell::new(None),
+            response_start: Cell::new(None),
+            start_pulse_us: Cell::new(None),
+        }
+    }
+
+    /// Answer the first `count` start pulses with `waveform` instead.
+    pub fn with_first_responses(mut self, waveform: Waveform, count: u32) -> Self {
+        self.first_responses = Some((waveform, count));
+        self
+    }
+
+    /// Number of start pulses the sensor answered.
+    pub fn triggers(&self) -> u32 {
+        self.triggers.get()
+    }
+
+    pub fn pin(&self) -> MockPin<'_> {
+        MockPin { sim: self }
+    }
+
+    pub fn clock(&self) -> MockClock<'_> {
+        MockClock { sim: self }
+    }
+
+    /// Duration of the last start pulse sent by the host.
+    pub fn start_pulse_us(&self) -> Option<u64> {
+        self.start_pulse_us.get()
+    }
+
+    pub fn now_us(&self) -> u64 {
+        self.now_us.get()
+    }
+
+    fn advance(&self, us: u64) {
+        self.now_us.set(self.now_us.get() + us);
+    }
+
+    fn line_is_high(&self) -> bool {
+        if self.host_low_since.get().is_some() {
+            return false;
+        }
+        let waveform = match self.first_responses {
+            Some((ref waveform, count)) if self.triggers.get() <= count => waveform,
+            _ => &self.waveform,
+        };
+        match self.response_start.get() {
+            Some(start) => waveform.level_at(self.now_us.get() - start),
+            None => true,
+        }
+    }
+}
+
+/// Open-drain pin connected to the simulated sensor.
+pub struct MockPin<'a> {
+    sim: &'a Simulation,
+}
+
+impl ErrorType for MockPin<'_> {
+    type Error = Infallible;
+}
+
+impl InputPin for MockPin<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let level = self.sim.line_is_high();
+        self.sim.advance(POLL_COST_US);
+        Ok(level)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+impl OutputPin for MockPin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        if self.sim.host_low_since.get().is_none() {
+            self.sim.host_low_since.set(Some(self.sim.now_us.get()));
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        if let Some(since) = self.sim.host_low_since.take() {
+            let now = self.sim.now_us.get();
+            self.sim.start_pulse_us.set(Some(now - since));
+            if now - since >= MIN_START_PULSE_US {
+                self.sim.response_start.set(Some(now));
+                self.sim.triggers.set(self.sim.triggers.get() + 1);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Wait for MockPin<'_> {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        while !self.sim.line_is_high() {
+            yield_now().await;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        while self.sim.line_is_high() {
+            yield_now().await;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_low().await?;
+        self.wait_for_high().await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_high().await?;
+        self.wait_for_low().await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let level = self.sim.line_is_high();
+        while self.sim.line_is_high() == level {
+            yield_now().await;
+        }
+        Ok(())
+    }
+}
+
+/// Clock of the simulation, advancing only on waits.
+#[derive(Clone)]
+pub struct MockClock<'a> {
+    sim: &'a Simulation,
+}
+
+impl Clock for MockClock<'_> {
+    fn now_micros(&mut self) -> u64 {
+        self.sim.now_us.get()
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.sim.advance(us as u64);
+    }
+}
+
+impl DelayNs for MockClock<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        let deadline = self.sim.now_us.get() + (ns as u64).div_ceil(1_000);
+        while self.sim.now_us.get() < deadline {
+            // Skip to the last millisecond of long delays at once, to keep the
+            // simulation of long periods fast.
+            let remaining_us = deadline - self.sim.now_us.get();
+            self.sim
+                .advance(remaining_us.saturating_sub(LONG_DELAY_US).max(1));
+            yield_now().await;
+        }
+    }
+}
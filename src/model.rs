@@ -0,0 +1,92 @@
+use core::ops::RangeInclusive;
+
+use defmt::Format;
+
+/// Sensors sharing the AM2301 one-wire protocol, which differ by their start
+/// pulse, the layout of their data and their measuring ranges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Format)]
+pub enum SensorModel {
+    /// AM2301, 16 bits humidity and sign-magnitude temperature in tenths.
+    #[default]
+    Am2301,
+    /// AM2302, same protocol as the AM2301.
+    Am2302,
+    /// DHT11, integral and decimal bytes for both humidity and temperature.
+    Dht11,
+    /// DHT21, same sensor as the AM2301.
+    Dht21,
+    /// DHT22, same sensor as the AM2302.
+    Dht22,
+}
+
+impl SensorModel {
+    /// Duration of the low pulse the host must send to start a measure.
+    pub fn start_pulse_us(&self) -> u32 {
+        match self {
+            Self::Dht11 => 18_000,
+            Self::Am2301 | Self::Am2302 | Self::Dht21 | Self::Dht22 => 1_000,
+        }
+    }
+
+    /// Humidity range of the sensor, in tenths of %.
+    pub fn humidity_range_decipercent(&self) -> RangeInclusive<u16> {
+        match self {
+            Self::Dht11 => 200..=900,
+            Self::Am2301 | Self::Am2302 | Self::Dht21 | Self::Dht22 => 0..=1000,
+        }
+    }
+
+    /// Temperature range of the sensor, in tenths of degree Celsius.
+    pub fn temperature_range_decicelsius(&self) -> RangeInclusive<i16> {
+        match self {
+            Self::Dht11 => 0..=500,
+            Self::Am2301 | Self::Am2302 | Self::Dht21 | Self::Dht22 => -400..=800,
+        }
+    }
+
+    /// Decode the humidity and temperature, in tenths of % and of degree
+    /// Celsius, from the 4 data bytes of a frame.
+    pub(crate) fn decode(&self, data: [u8; 4]) -> (u16, i16) {
+        match self {
+            Self::Dht11 => {
+                let humidity = data[0] as u16 * 10 + data[1] as u16;
+                let temperature = data[2] as i16 * 10 + (data[3] & 0x7f) as i16;
+                match data[3] & 0x80 {
+                    0 => (humidity, temperature),
+                    _ => (humidity, -temperature),
+                }
+            }
+            Self::Am2301 | Self::Am2302 | Self::Dht21 | Self::Dht22 => {
+                let humidity = u16::from_be_bytes([data[0], data[1]]);
+                let temperature = i16::from_be_bytes([data[2] & 0x7f, data[3]]);
+                match data[2] & 0x80 {
+                    0 => (humidity, temperature),
+                    _ => (humidity, -temperature),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_am2301_frame() {
+        assert_eq!(SensorModel::Am2301.decode([2, 146, 1, 13]), (658, 269));
+        assert_eq!(SensorModel::Dht22.decode([2, 146, 129, 13]), (658, -269));
+    }
+
+    #[test]
+    fn decode_dht11_frame() {
+        assert_eq!(SensorModel::Dht11.decode([45, 0, 23, 4]), (450, 234));
+        assert_eq!(SensorModel::Dht11.decode([45, 0, 1, 132]), (450, -14));
+    }
+
+    #[test]
+    fn dht11_needs_a_longer_start_pulse() {
+        assert_eq!(SensorModel::Dht11.start_pulse_us(), 18_000);
+        assert_eq!(SensorModel::Am2302.start_pulse_us(), 1_000);
+    }
+}
@@ -0,0 +1,126 @@
+use defmt::Format;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+
+use crate::clock::Clock;
+use crate::driver::{Am2301, MIN_INTERVAL_US};
+use crate::reading::Reading;
+use crate::MeasureError;
+
+/// Index of a sensor within an [`Am2301Array`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Format)]
+pub struct SensorId(pub u8);
+
+/// Several sensors on their own pins, measured one after the other.
+///
+/// The first measures are staggered across the 2s interval of the sensors, so
+/// that measuring them in turn spreads the blocking measures evenly over time
+/// while each sensor keeps its own interval.
+pub struct Am2301Array<P, C, D, const N: usize> {
+    sensors: [Am2301<P, C, D>; N],
+    next: usize,
+}
+
+impl<P, C, D, const N: usize> Am2301Array<P, C, D, N>
+where
+    P: InputPin + OutputPin,
+    C: Clock + Clone,
+    D: DelayNs + Clone,
+{
+    /// Create an array of AM2301 sensors connected to the open-drain `pins`.
+    pub fn from_pins(pins: [P; N], clock: C, delay: D) -> Self {
+        Self::new(pins.map(|pin| Am2301::new(pin, clock.clone(), delay.clone())))
+    }
+}
+
+impl<P, C, D, const N: usize> Am2301Array<P, C, D, N>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+    D: DelayNs,
+{
+    /// Create an array from individually configured drivers, with a model or
+    /// a calibration of their own. Their [`crate::IntervalPolicy`] applies
+    /// when measured in turn, so they should wait for the sensor (the
+    /// default) for the measures to stay staggered.
+    ///
+    /// The array must hold 1 to 256 sensors, as numbered by [`SensorId`],
+    /// which is checked at compile time.
+    pub fn new(mut sensors: [Am2301<P, C, D>; N]) -> Self {
+        const {
+            assert!(
+                N > 0 && N <= u8::MAX as usize + 1,
+                "an array holds 1 to 256 sensors"
+            )
+        };
+        for (idx, sensor) in sensors.iter_mut().enumerate() {
+            sensor.postpone(MIN_INTERVAL_US * idx as u64 / N as u64);
+        }
+        Self { sensors, next: 0 }
+    }
+
+    /// Measure the next sensor in turn, once it is ready.
+    pub async fn measure_next(&mut self) -> (SensorId, Result<Reading, MeasureError>) {
+        let idx = self.next;
+        self.next = (idx + 1) % N;
+        (SensorId(idx as u8), self.sensors[idx].measure().await)
+    }
+
+    /// Driver of a given sensor.
+    pub fn sensor(&mut self, id: SensorId) -> Option<&mut Am2301<P, C, D>> {
+        self.sensors.get_mut(id.0 as usize)
+    }
+
+    /// Release the drivers of the sensors.
+    pub fn free(self) -> [Am2301<P, C, D>; N] {
+        self.sensors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::mock::{Simulation, Waveform, DATASHEET_FRAME};
+
+    #[test]
+    fn measure_sensors_in_turn() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensors =
+            Am2301Array::from_pins([sim.pin(), sim.pin(), sim.pin()], sim.clock(), sim.clock());
+
+        let ids = [(); 4].map(|_| block_on(sensors.measure_next()));
+
+        assert!(matches!(
+            ids,
+            [
+                (SensorId(0), Ok(_)),
+                (SensorId(1), Ok(_)),
+                (SensorId(2), Ok(_)),
+                (SensorId(0), Ok(_)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn measures_are_staggered() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let mut sensors = Am2301Array::from_pins([sim.pin(), sim.pin()], sim.clock(), sim.clock());
+
+        for _ in 0..4 {
+            let _ = block_on(sensors.measure_next());
+        }
+
+        let [first, second] = [SensorId(0), SensorId(1)].map(|id| {
+            sensors
+                .sensor(id)
+                .unwrap()
+                .last_measure_at()
+                .unwrap()
+                .as_micros()
+        });
+        assert_eq!(second - first, MIN_INTERVAL_US / 2);
+        assert!(sensors.sensor(SensorId(2)).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+//! Quantities derived from the temperature and relative humidity of a
+//! [`Measure`], for moist air at sea level pressure.
+
+use libm::{expf, fabsf, logf, sqrtf};
+
+use crate::units::ZERO_CELSIUS_KELVIN;
+use crate::Measure;
+
+/// Coefficients of the Magnus formula over water (Alduchov & Eskridge, 1996),
+/// accurate within 0.4% from -40 to 50°C.
+const MAGNUS_A_HPA: f32 = 6.1094;
+const MAGNUS_B: f32 = 17.625;
+const MAGNUS_C_CELSIUS: f32 = 243.04;
+
+/// Inverse of the specific gas constant of water vapour, in g.K/J, times 100
+/// to take pressures in hPa.
+const WATER_VAPOUR_G_K_PER_HPA_M3: f32 = 216.7;
+
+/// Saturation vapour pressure over water at `celsius`, in hPa.
+fn saturation_vapour_pressure_hpa(celsius: f32) -> f32 {
+    MAGNUS_A_HPA * expf(MAGNUS_B * celsius / (MAGNUS_C_CELSIUS + celsius))
+}
+
+impl Measure {
+    /// Partial pressure of water vapour, in hPa.
+    pub fn vapour_pressure_hpa(&self) -> f32 {
+        self.humidity_f32() / 100.0 * saturation_vapour_pressure_hpa(self.temperature_f32())
+    }
+
+    /// Temperature in degree Celsius at which the air would be saturated.
+    /// Not defined for a 0% humidity, for which it returns NaN.
+    pub fn dew_point_celsius(&self) -> f32 {
+        let gamma = logf(self.humidity_f32() / 100.0)
+            + MAGNUS_B * self.temperature_f32() / (MAGNUS_C_CELSIUS + self.temperature_f32());
+        MAGNUS_C_CELSIUS * gamma / (MAGNUS_B - gamma)
+    }
+
+    /// Mass of water vapour per volume of air, in g/m³.
+    pub fn absolute_humidity_g_m3(&self) -> f32 {
+        WATER_VAPOUR_G_K_PER_HPA_M3 * self.vapour_pressure_hpa()
+            / (ZERO_CELSIUS_KELVIN + self.temperature_f32())
+    }
+
+    /// Vapour pressure deficit, the difference between the saturation and
+    /// actual vapour pressures, in kPa.
+    pub fn vapour_pressure_deficit_kpa(&self) -> f32 {
+        let saturation_hpa = saturation_vapour_pressure_hpa(self.temperature_f32());
+        (saturation_hpa - self.vapour_pressure_hpa()) / 10.0
+    }
+
+    /// Apparent temperature in degree Celsius, as computed by the US National
+    /// Weather Service (Rothfusz regression with its adjustments).
+    pub fn heat_index_celsius(&self) -> f32 {
+        let t = self.temperature_f32() * 9.0 / 5.0 + 32.0;
+        let rh = self.humidity_f32();
+
+        let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+        let fahrenheit = if (simple + t) / 2.0 < 80.0 {
+            simple
+        } else {
+            let mut hi = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+                - 0.224_755_4 * t * rh
+                - 0.006_837_83 * t * t
+                - 0.054_817_17 * rh * rh
+                + 0.001_228_74 * t * t * rh
+                + 0.000_852_82 * t * rh * rh
+                - 0.000_001_99 * t * t * rh * rh;
+            if rh < 13.0 && (80.0..=112.0).contains(&t) {
+                hi -= (13.0 - rh) / 4.0 * sqrtf((17.0 - fabsf(t - 95.0)) / 17.0);
+            } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+                hi += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+            }
+            hi
+        };
+        (fahrenheit - 32.0) * 5.0 / 9.0
+    }
+
+    /// Humidex, the perceived temperature in degree Celsius as defined by
+    /// Environment Canada.
+    pub fn humidex_celsius(&self) -> f32 {
+        self.temperature_f32() + 0.5555 * (self.vapour_pressure_hpa() - 10.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::measure;
+
+    fn assert_close(value: f32, expected: f32, tolerance: f32) {
+        assert!(
+            fabsf(value - expected) <= tolerance,
+            "{value} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn dew_point() {
+        assert_close(measure(600, 250).dew_point_celsius(), 16.7, 0.1);
+        assert_close(measure(1000, 200).dew_point_celsius(), 20.0, 0.01);
+        assert_close(measure(500, -100).dew_point_celsius(), -18.4, 0.2);
+        assert!(measure(0, 200).dew_point_celsius().is_nan());
+    }
+
+    #[test]
+    fn absolute_humidity() {
+        assert_close(measure(600, 250).absolute_humidity_g_m3(), 13.8, 0.1);
+        assert_close(measure(1000, 300).absolute_humidity_g_m3(), 30.4, 0.2);
+        assert_close(measure(1000, 0).absolute_humidity_g_m3(), 4.85, 0.05);
+    }
+
+    #[test]
+    fn vapour_pressure_deficit() {
+        assert_close(measure(600, 250).vapour_pressure_deficit_kpa(), 1.27, 0.01);
+        assert_close(measure(1000, 250).vapour_pressure_deficit_kpa(), 0.0, 0.001);
+        assert_close(measure(0, 200).vapour_pressure_deficit_kpa(), 2.34, 0.01);
+    }
+
+    #[test]
+    fn heat_index() {
+        // NWS table: 90°F at 60% feels like 100°F, 80°F at 40% like 80°F.
+        assert_close(measure(600, 322).heat_index_celsius(), 37.8, 0.3);
+        assert_close(measure(400, 267).heat_index_celsius(), 26.7, 0.3);
+        // 100°F at 50% feels like 118°F.
+        assert_close(measure(500, 378).heat_index_celsius(), 47.8, 0.3);
+    }
+
+    #[test]
+    fn humidex() {
+        // Environment Canada table: 30°C with a 15°C dew point gives 34.
+        assert_close(measure(402, 300).humidex_celsius(), 34.0, 0.5);
+        // 25°C with a 20°C dew point gives 32.6, shown as 33.
+        assert_close(measure(738, 250).humidex_celsius(), 32.6, 0.3);
+    }
+}
@@ -0,0 +1,121 @@
+use defmt::Format;
+
+/// Number of edges of a complete frame: the 2 edges of the start pulse, the 3
+/// edges of the sensor acknowledge and handshake, and 2 edges per data bit.
+pub const FRAME_EDGES: usize = 85;
+
+/// Line level held between two consecutive edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct Pulse {
+    pub high: bool,
+    pub duration_us: u32,
+}
+
+/// Waveform of a frame, from the start pulse of the host to the end of the
+/// last data bit, as seen by the host.
+///
+/// The line is low after even edges and high after odd ones: edge 0 is the
+/// host pulling the line low, edge 1 the host releasing it, edge 2 the sensor
+/// acknowledging the start pulse, and so on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct RawFrame {
+    edges_us: [u32; FRAME_EDGES],
+    len: usize,
+    origin_us: u64,
+    polls: u32,
+}
+
+impl RawFrame {
+    pub(crate) fn new() -> Self {
+        Self {
+            edges_us: [0; FRAME_EDGES],
+            len: 0,
+            origin_us: 0,
+            polls: 0,
+        }
+    }
+
+    /// Record the first edge, taking its timestamp as the origin of the frame.
+    pub(crate) fn start(&mut self, timestamp_us: u64) {
+        self.origin_us = timestamp_us;
+        self.len = 0;
+        self.polls = 0;
+        self.push(timestamp_us);
+    }
+
+    pub(crate) fn count_poll(&mut self) {
+        self.polls = self.polls.saturating_add(1);
+    }
+
+    pub(crate) fn push(&mut self, timestamp_us: u64) {
+        if self.len < FRAME_EDGES {
+            self.edges_us[self.len] = (timestamp_us - self.origin_us) as u32;
+            self.len += 1;
+        }
+    }
+
+    /// Timestamps in µs of the edges captured so far, relative to the start
+    /// of the start pulse.
+    pub fn edges_us(&self) -> &[u32] {
+        &self.edges_us[..self.len]
+    }
+
+    /// Whether all the edges of the frame were captured.
+    pub fn is_complete(&self) -> bool {
+        self.len == FRAME_EDGES
+    }
+
+    /// Number of times the pin was read while waiting for the edges.
+    pub fn polls(&self) -> u32 {
+        self.polls
+    }
+
+    /// Levels held between consecutive edges, starting with the start pulse.
+    pub fn pulses(&self) -> impl Iterator<Item = Pulse> + '_ {
+        self.edges_us()
+            .windows(2)
+            .enumerate()
+            .map(|(idx, edges)| Pulse {
+                high: idx % 2 == 1,
+                duration_us: edges[1] - edges[0],
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulses_alternate_from_low() {
+        let mut raw = RawFrame::new();
+        raw.start(1_000);
+        raw.push(2_000);
+        raw.push(2_030);
+
+        assert_eq!(raw.edges_us(), [0, 1_000, 1_030]);
+        assert!(raw.pulses().eq([
+            Pulse {
+                high: false,
+                duration_us: 1_000
+            },
+            Pulse {
+                high: true,
+                duration_us: 30
+            },
+        ]));
+        assert!(!raw.is_complete());
+    }
+
+    #[test]
+    fn extra_edges_are_dropped() {
+        let mut raw = RawFrame::new();
+        raw.start(0);
+        for ts in 1..2 * FRAME_EDGES as u64 {
+            raw.push(ts);
+        }
+
+        assert!(raw.is_complete());
+        assert_eq!(raw.edges_us().len(), FRAME_EDGES);
+    }
+}
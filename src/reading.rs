@@ -0,0 +1,53 @@
+use defmt::Format;
+use embassy_time::{Duration, Instant};
+
+use crate::Measure;
+
+/// Measure taken by a stateful driver, with when it was taken so that stale
+/// values can be discarded.
+///
+/// Timestamps come from the [`crate::Clock`] of the driver, which may not
+/// share the time base of `embassy_time::Instant::now`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct Reading {
+    pub measure: Measure,
+    /// When the frame of the measure was fully received.
+    pub at: Instant,
+    /// Number of measures triggered by the driver before this one, failed
+    /// ones included, so that gaps in the sequence reveal failed measures.
+    pub sequence: u32,
+}
+
+impl Reading {
+    /// Time elapsed between the measure and `now`, both in the time base of
+    /// the [`crate::Clock`] of the driver. See [`crate::Am2301::age`].
+    pub fn age_at(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn age_of_a_reading() {
+        let reading = Reading {
+            measure: Measure {
+                humidity_decipercent: 658,
+                temperature_decicelsius: 269,
+            },
+            at: Instant::from_secs(10),
+            sequence: 0,
+        };
+
+        assert_eq!(
+            reading.age_at(Instant::from_secs(12)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            reading.age_at(Instant::from_secs(9)),
+            Duration::from_secs(0)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+
+use crate::clock::Clock;
+use crate::driver::MIN_INTERVAL_US;
+use crate::{measure_once_blocking, Measure, MeasureError, SensorModel};
+
+/// How failed measures are retried.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum number of measures, including the first one.
+    pub max_attempts: u8,
+    /// Delay between two attempts, raised to the 2s minimum interval of the
+    /// sensor.
+    pub backoff_ms: u32,
+    /// Whether a failed measure is worth retrying.
+    pub retry_if: fn(&MeasureError) -> bool,
+}
+
+impl RetryPolicy {
+    pub(crate) fn backoff_ms(&self) -> u32 {
+        self.backoff_ms.max((MIN_INTERVAL_US / 1_000) as u32)
+    }
+
+    pub(crate) fn should_retry(&self, attempts: u8, err: &MeasureError) -> bool {
+        attempts < self.max_attempts && (self.retry_if)(err)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Up to 3 attempts, 2s apart, retrying only transient errors.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff_ms: (MIN_INTERVAL_US / 1_000) as u32,
+            retry_if: MeasureError::is_transient,
+        }
+    }
+}
+
+/// Successful measure, or [`crate::Reading`] for the driver, along with the
+/// number of attempts it took.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetriedMeasure<T = Measure> {
+    pub measure: T,
+    /// Number of measures performed, 1 if the first one succeeded.
+    pub attempts: u8,
+}
+
+/// Retrieve a single measure from the `model` sensor connected to an
+/// open-drain pin, retrying failed measures according to `policy`. Returns
+/// the last error if no attempt succeeded.
+pub async fn measure_with_retry<P, C, D>(
+    pin: &mut P,
+    clock: &mut C,
+    delay: &mut D,
+    model: SensorModel,
+    policy: &RetryPolicy,
+) -> Result<RetriedMeasure, MeasureError>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+    D: DelayNs,
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match measure_once_blocking(pin, clock, model) {
+            Ok(measure) => return Ok(RetriedMeasure { measure, attempts }),
+            Err(err) if policy.should_retry(attempts, &err) => {
+                delay.delay_ms(policy.backoff_ms()).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::mock::{Simulation, Waveform, DATASHEET_FRAME};
+
+    #[test]
+    fn first_attempt_succeeds() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+
+        let res = block_on(measure_with_retry(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            SensorModel::Am2301,
+            &RetryPolicy::default(),
+        ));
+
+        assert!(matches!(res, Ok(RetriedMeasure { attempts: 1, .. })));
+    }
+
+    #[test]
+    fn retry_transient_errors_respecting_sensor_interval() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME))
+            .with_first_responses(Waveform::corrupted_datasheet(), 2);
+
+        let res = block_on(measure_with_retry(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            SensorModel::Am2301,
+            &RetryPolicy {
+                backoff_ms: 10,
+                ..RetryPolicy::default()
+            },
+        ));
+
+        assert!(matches!(res, Ok(RetriedMeasure { attempts: 3, .. })));
+        assert!(sim.now_us() >= 2 * MIN_INTERVAL_US);
+    }
+
+    #[test]
+    fn give_up_after_max_attempts() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME))
+            .with_first_responses(Waveform::corrupted_datasheet(), 5);
+
+        let res = block_on(measure_with_retry(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            SensorModel::Am2301,
+            &RetryPolicy {
+                max_attempts: 4,
+                ..RetryPolicy::default()
+            },
+        ));
+
+        assert!(matches!(res, Err(MeasureError::ChecksumError { .. })));
+        assert_eq!(sim.triggers(), 4);
+    }
+
+    #[test]
+    fn do_not_retry_non_retriable_errors() {
+        let sim = Simulation::new(Waveform::empty());
+
+        let res = block_on(measure_with_retry(
+            &mut sim.pin(),
+            &mut sim.clock(),
+            &mut sim.clock(),
+            SensorModel::Am2301,
+            &RetryPolicy::default(),
+        ));
+
+        assert!(matches!(res, Err(MeasureError::NoAcknowledgeError)));
+        assert!(sim.now_us() < 2_000);
+    }
+}
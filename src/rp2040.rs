@@ -0,0 +1,74 @@
+use core::convert::Infallible;
+
+use embassy_rp::gpio::Flex;
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+use embedded_hal_async::digital::Wait;
+
+/// Open-drain view of an RP2040 [`Flex`] pin.
+///
+/// Driving the pin low switches it to output, while driving it high releases
+/// the line by switching it back to input, so that the sensor can drive it.
+pub struct FlexOpenDrain<'a, 'd> {
+    pin: &'a mut Flex<'d>,
+}
+
+impl<'a, 'd> FlexOpenDrain<'a, 'd> {
+    pub fn new(pin: &'a mut Flex<'d>) -> Self {
+        Self { pin }
+    }
+}
+
+impl ErrorType for FlexOpenDrain<'_, '_> {
+    type Error = Infallible;
+}
+
+impl InputPin for FlexOpenDrain<'_, '_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(Flex::is_high(self.pin))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(Flex::is_low(self.pin))
+    }
+}
+
+impl OutputPin for FlexOpenDrain<'_, '_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Flex::set_low(self.pin);
+        self.pin.set_as_output();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Flex::set_high(self.pin);
+        self.pin.set_as_input();
+        Ok(())
+    }
+}
+
+impl Wait for FlexOpenDrain<'_, '_> {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        Flex::wait_for_high(self.pin).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        Flex::wait_for_low(self.pin).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Flex::wait_for_rising_edge(self.pin).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Flex::wait_for_falling_edge(self.pin).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Flex::wait_for_any_edge(self.pin).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::gpio::{Level, Pull};
+use embassy_rp::pio::{
+    Common, Config, Direction, Instance, Pin, PioPin, ShiftConfig, ShiftDirection, StateMachine,
+};
+use embassy_time::{with_deadline, Duration, Instant};
+use fixed::traits::ToFixed;
+use pio::{InstructionOperands, JmpCondition};
+
+use crate::{convert_bytes_to_bits, measure_from_bits, Measure, MeasureError, SensorModel};
+
+/// Upper bound of a whole transaction after the start pulse: handshake and
+/// 40 bits.
+const FRAME_TIMEOUT: Duration = Duration::from_millis(6);
+
+/// AM2301 reader running on a PIO state machine of the RP2040.
+///
+/// The state machine generates the start pulse and samples the 40 data bits
+/// itself, pushing each received byte into its RX FIFO, so that the measure is
+/// immune to interrupts firing on the CPU during the transaction.
+pub struct PioAm2301<'d, PIO: Instance, const SM: usize> {
+    sm: StateMachine<'d, PIO, SM>,
+    pin: Pin<'d, PIO>,
+    origin: u8,
+    model: SensorModel,
+}
+
+impl<'d, PIO: Instance, const SM: usize> PioAm2301<'d, PIO, SM> {
+    /// Load the reader program in the PIO block and configure `sm` to drive the
+    /// AM2301 sensor connected to `pin`.
+    pub fn new(
+        common: &mut Common<'d, PIO>,
+        mut sm: StateMachine<'d, PIO, SM>,
+        pin: impl PioPin,
+    ) -> Self {
+        // One cycle per microsecond. The line is driven low by switching the
+        // pin to output, its output value being kept low.
+        let program = pio_proc::pio_asm!(
+            "    pull block",
+            "    mov x, osr",
+            "    set pindirs, 1",
+            "start_pulse:",
+            "    jmp x-- start_pulse",
+            "    set pindirs, 0",
+            // Sensor acknowledge: 80us low then 80us high.
+            "    wait 0 pin 0",
+            "    wait 1 pin 0",
+            ".wrap_target",
+            // Each bit starts with a 50us low pulse, followed by a 26-28us
+            // high pulse for a 0 and a 70us one for a 1: sample after 40us.
+            "    wait 0 pin 0",
+            "    wait 1 pin 0 [31]",
+            "    nop [7]",
+            "    in pins, 1",
+            ".wrap",
+        );
+
+        let mut pin = common.make_pio_pin(pin);
+        pin.set_pull(Pull::Up);
+        let program = common.load_program(&program.program);
+
+        let mut config = Config::default();
+        config.use_program(&program, &[]);
+        config.set_set_pins(&[&pin]);
+        config.set_in_pins(&[&pin]);
+        config.clock_divider = (clk_sys_freq() / 1_000_000).to_fixed();
+        config.shift_in = ShiftConfig {
+            threshold: 8,
+            direction: ShiftDirection::Left,
+            auto_fill: true,
+        };
+        sm.set_config(&config);
+
+        Self {
+            sm,
+            pin,
+            origin: program.origin,
+            model: SensorModel::Am2301,
+        }
+    }
+
+    /// Drive another sensor sharing the AM2301 protocol.
+    pub fn with_model(mut self, model: SensorModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Retrieve a single measure from the sensor.
+    /// Will timeout if no matching sensor is connected to the pin.
+    ///
+    /// Bits are only reported at byte granularity: a timeout reports the first
+    /// bit of the incomplete byte, and no sensor answer at all is reported as
+    /// [`MeasureError::NoAcknowledgeError`].
+    pub async fn measure(&mut self) -> Result<Measure, MeasureError> {
+        let deadline = Instant::now()
+            + Duration::from_micros(self.model.start_pulse_us() as u64)
+            + FRAME_TIMEOUT;
+        self.start();
+
+        let mut frame = [0u8; 5];
+        for idx in 0..frame.len() {
+            match with_deadline(deadline, self.sm.rx().wait_pull()).await {
+                Ok(word) => frame[idx] = word as u8,
+                Err(_) => {
+                    self.sm.set_enable(false);
+                    return Err(match idx {
+                        0 => MeasureError::NoAcknowledgeError,
+                        _ => MeasureError::MeasureTimeoutError {
+                            bit: idx as u8 * 8,
+                            low_us: None,
+                            previous_high_us: None,
+                            frame,
+                        },
+                    });
+                }
+            }
+        }
+        self.sm.set_enable(false);
+
+        measure_from_bits(convert_bytes_to_bits(frame), self.model)
+    }
+
+    fn start(&mut self) {
+        self.sm.set_enable(false);
+        self.sm.restart();
+        self.sm.clear_fifos();
+        self.sm.set_pins(Level::Low, &[&self.pin]);
+        self.sm.set_pin_dirs(Direction::In, &[&self.pin]);
+        let jump = InstructionOperands::JMP {
+            condition: JmpCondition::Always,
+            address: self.origin,
+        };
+        // SAFETY: jumps to the start of the program loaded in `new`.
+        unsafe { self.sm.exec_instr(jump.encode()) };
+        // `jmp x--` loops x + 1 times.
+        self.sm.tx().push(self.model.start_pulse_us() - 1);
+        self.sm.set_enable(true);
+    }
+}
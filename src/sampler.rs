@@ -0,0 +1,155 @@
+use defmt::Format;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::watch::Watch;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+
+use crate::clock::Clock;
+use crate::driver::{Am2301, IntervalPolicy, MIN_INTERVAL_US};
+use crate::reading::Reading;
+use crate::MeasureError;
+
+/// Latest state of a sensor sampled by a [`Sampler`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Format)]
+pub struct Sample {
+    /// Last successful reading, if any.
+    pub reading: Option<Reading>,
+    /// Error of the last measure, if it failed.
+    pub last_error: Option<MeasureError>,
+    /// Number of successful measures so far.
+    pub successes: u32,
+    /// Number of failed measures so far.
+    pub failures: u32,
+}
+
+/// Background service measuring a sensor periodically, and publishing the
+/// latest [`Sample`] to any number of tasks through a [`Watch`].
+pub struct Sampler<P, C, D> {
+    sensor: Am2301<P, C, D>,
+    period_us: u64,
+    sample: Sample,
+}
+
+impl<P, C, D> Sampler<P, C, D>
+where
+    P: InputPin + OutputPin,
+    C: Clock,
+    D: DelayNs,
+{
+    /// Sample `sensor` every 2s, the minimum interval of the sensor.
+    pub fn new(sensor: Am2301<P, C, D>) -> Self {
+        Self {
+            sensor: sensor.with_policy(IntervalPolicy::Wait),
+            period_us: MIN_INTERVAL_US,
+            sample: Sample::default(),
+        }
+    }
+
+    /// Change the sampling period, clamped to the 2s minimum interval of the
+    /// sensor.
+    pub fn with_period_ms(mut self, period_ms: u32) -> Self {
+        self.period_us = (period_ms as u64 * 1_000).max(MIN_INTERVAL_US);
+        self
+    }
+
+    /// Measure the sensor forever, sending each new sample to `watch`.
+    pub async fn run<M: RawMutex, const N: usize>(mut self, watch: &Watch<M, Sample, N>) -> ! {
+        let sender = watch.sender();
+        loop {
+            sender.send(self.sample().await);
+        }
+    }
+
+    /// Wait for the next period, and measure the sensor.
+    async fn sample(&mut self) -> Sample {
+        let res = self.sensor.measure().await;
+        self.sensor.postpone(self.period_us - MIN_INTERVAL_US);
+        match res {
+            Ok(reading) => {
+                self.sample.reading = Some(reading);
+                self.sample.last_error = None;
+                self.sample.successes += 1;
+            }
+            Err(err) => {
+                self.sample.last_error = Some(err);
+                self.sample.failures += 1;
+            }
+        }
+        self.sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use embassy_futures::select::{select, Either};
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    use super::*;
+    use crate::mock::{Simulation, Waveform, DATASHEET_FRAME};
+
+    #[test]
+    fn count_successes_and_failures() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME))
+            .with_first_responses(Waveform::corrupted_datasheet(), 1);
+        let mut sampler = Sampler::new(Am2301::new(sim.pin(), sim.clock(), sim.clock()));
+
+        let first = block_on(sampler.sample());
+        let second = block_on(sampler.sample());
+
+        assert!(matches!(
+            first,
+            Sample {
+                reading: None,
+                last_error: Some(MeasureError::ChecksumError { .. }),
+                successes: 0,
+                failures: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            second,
+            Sample {
+                reading: Some(Reading { sequence: 1, .. }),
+                last_error: None,
+                successes: 1,
+                failures: 1,
+            }
+        ));
+    }
+
+    #[test]
+    fn publish_samples_at_the_given_period() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        let sampler =
+            Sampler::new(Am2301::new(sim.pin(), sim.clock(), sim.clock())).with_period_ms(5_000);
+        let watch: Watch<NoopRawMutex, Sample, 2> = Watch::new();
+        let mut first = watch.receiver().unwrap();
+        let mut second = watch.receiver().unwrap();
+
+        let received = block_on(select(sampler.run(&watch), async {
+            let mut measured_at = [0; 2];
+            for at in measured_at.iter_mut() {
+                *at = first.changed().await.reading.unwrap().at.as_micros();
+            }
+            measured_at
+        }));
+
+        let Either::Second([start, next]) = received;
+        assert_eq!(next - start, 5_000_000);
+        assert_eq!(second.try_get().map(|sample| sample.successes), Some(2));
+    }
+
+    #[test]
+    fn periods_beyond_u32_microseconds() {
+        let sim = Simulation::new(Waveform::from_bytes(DATASHEET_FRAME));
+        // 2h, more than the 71 minutes u32::MAX µs amounts to.
+        let mut sampler = Sampler::new(Am2301::new(sim.pin(), sim.clock(), sim.clock()))
+            .with_period_ms(7_200_000);
+
+        let first = block_on(sampler.sample()).reading.unwrap();
+        let second = block_on(sampler.sample()).reading.unwrap();
+
+        assert_eq!(second.at.as_micros() - first.at.as_micros(), 7_200_000_000);
+    }
+}
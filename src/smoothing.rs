@@ -0,0 +1,251 @@
+use crate::Measure;
+
+/// Filter turning a stream of measures into a smoothed one.
+pub trait Smoothing {
+    /// Feed a new measure, returning the smoothed measure.
+    fn update(&mut self, measure: Measure) -> Measure;
+
+    /// Forget the measures fed so far.
+    fn reset(&mut self);
+}
+
+/// Divide, rounding to the nearest integer, halves away from zero.
+fn div_round(numerator: i32, denominator: i32) -> i32 {
+    let half = denominator / 2;
+    if numerator >= 0 {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    }
+}
+
+/// Fixed-size ring buffer of the last `N` measures.
+#[derive(Clone, Copy, Debug)]
+struct Window<const N: usize> {
+    measures: [Measure; N],
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> Window<N> {
+    fn new() -> Self {
+        const { assert!(N > 0, "window must hold at least one measure") };
+        Self {
+            measures: [Measure {
+                humidity_decipercent: 0,
+                temperature_decicelsius: 0,
+            }; N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Add a measure, evicting the oldest one when full.
+    fn push(&mut self, measure: Measure) -> Option<Measure> {
+        let evicted = (self.len == N).then_some(self.measures[self.next]);
+        self.measures[self.next] = measure;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        evicted
+    }
+
+    fn measures(&self) -> &[Measure] {
+        &self.measures[..self.len]
+    }
+}
+
+/// Median of the last `N` measures, humidity and temperature being taken
+/// independently. Discards outliers entirely as long as they are fewer than
+/// half of the window.
+#[derive(Clone, Copy, Debug)]
+pub struct MedianFilter<const N: usize> {
+    window: Window<N>,
+}
+
+impl<const N: usize> MedianFilter<N> {
+    /// Create an empty filter over the last `N` measures. An empty window
+    /// (`N` = 0) is rejected at compile time.
+    pub fn new() -> Self {
+        Self {
+            window: Window::new(),
+        }
+    }
+}
+
+impl<const N: usize> Default for MedianFilter<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Median of `values`, averaging the two middle values for an even count.
+fn median<const N: usize>(values: &mut [i32; N], len: usize) -> i32 {
+    let values = &mut values[..len];
+    values.sort_unstable();
+    match len % 2 {
+        1 => values[len / 2],
+        _ => div_round(values[len / 2 - 1] + values[len / 2], 2),
+    }
+}
+
+impl<const N: usize> Smoothing for MedianFilter<N> {
+    fn update(&mut self, measure: Measure) -> Measure {
+        self.window.push(measure);
+        let len = self.window.len;
+        let mut humidity = [0i32; N];
+        let mut temperature = [0i32; N];
+        for (idx, measure) in self.window.measures().iter().enumerate() {
+            humidity[idx] = measure.humidity_decipercent as i32;
+            temperature[idx] = measure.temperature_decicelsius as i32;
+        }
+        Measure {
+            humidity_decipercent: median(&mut humidity, len) as u16,
+            temperature_decicelsius: median(&mut temperature, len) as i16,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.window = Window::new();
+    }
+}
+
+/// Mean of the last `N` measures.
+#[derive(Clone, Copy, Debug)]
+pub struct MovingAverage<const N: usize> {
+    window: Window<N>,
+    humidity_sum: i32,
+    temperature_sum: i32,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Create an empty average over the last `N` measures. An empty window
+    /// (`N` = 0) is rejected at compile time.
+    pub fn new() -> Self {
+        Self {
+            window: Window::new(),
+            humidity_sum: 0,
+            temperature_sum: 0,
+        }
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Smoothing for MovingAverage<N> {
+    fn update(&mut self, measure: Measure) -> Measure {
+        if let Some(evicted) = self.window.push(measure) {
+            self.humidity_sum -= evicted.humidity_decipercent as i32;
+            self.temperature_sum -= evicted.temperature_decicelsius as i32;
+        }
+        self.humidity_sum += measure.humidity_decipercent as i32;
+        self.temperature_sum += measure.temperature_decicelsius as i32;
+
+        let len = self.window.len as i32;
+        Measure {
+            humidity_decipercent: div_round(self.humidity_sum, len) as u16,
+            temperature_decicelsius: div_round(self.temperature_sum, len) as i16,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Scale of the internal state of [`ExponentialAverage`], so that small
+/// smoothing factors do not get stuck on integer rounding.
+const EMA_SCALE: i32 = 100;
+
+/// Exponential moving average, weighting each new measure by
+/// `alpha_percent`% and the previous average by the rest.
+#[derive(Clone, Copy, Debug)]
+pub struct ExponentialAverage {
+    alpha_percent: u8,
+    /// Humidity and temperature, scaled by [`EMA_SCALE`].
+    state: Option<(i32, i32)>,
+}
+
+impl ExponentialAverage {
+    /// Create an average weighting new measures by `alpha_percent`%, clamped
+    /// to 1..=100. The lower, the smoother.
+    pub fn new(alpha_percent: u8) -> Self {
+        Self {
+            alpha_percent: alpha_percent.clamp(1, 100),
+            state: None,
+        }
+    }
+}
+
+impl Smoothing for ExponentialAverage {
+    fn update(&mut self, measure: Measure) -> Measure {
+        let humidity = measure.humidity_decipercent as i32 * EMA_SCALE;
+        let temperature = measure.temperature_decicelsius as i32 * EMA_SCALE;
+        let alpha = self.alpha_percent as i32;
+        let (humidity, temperature) = match self.state {
+            None => (humidity, temperature),
+            Some((last_humidity, last_temperature)) => (
+                last_humidity + div_round(alpha * (humidity - last_humidity), 100),
+                last_temperature + div_round(alpha * (temperature - last_temperature), 100),
+            ),
+        };
+        self.state = Some((humidity, temperature));
+        Measure {
+            humidity_decipercent: div_round(humidity, EMA_SCALE) as u16,
+            temperature_decicelsius: div_round(temperature, EMA_SCALE) as i16,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::measure;
+
+    #[test]
+    fn median_discards_outliers() {
+        let mut filter = MedianFilter::<3>::new();
+
+        assert_eq!(filter.update(measure(500, 200)), measure(500, 200));
+        assert_eq!(filter.update(measure(510, 210)), measure(505, 205));
+        assert_eq!(filter.update(measure(900, -300)), measure(510, 200));
+        assert_eq!(filter.update(measure(520, 220)), measure(520, 210));
+    }
+
+    #[test]
+    fn moving_average_over_window() {
+        let mut filter = MovingAverage::<2>::new();
+
+        assert_eq!(filter.update(measure(500, -10)), measure(500, -10));
+        assert_eq!(filter.update(measure(503, -15)), measure(502, -13));
+        assert_eq!(filter.update(measure(510, 20)), measure(507, 3));
+
+        filter.reset();
+        assert_eq!(filter.update(measure(400, 100)), measure(400, 100));
+    }
+
+    #[test]
+    fn exponential_average_converges() {
+        let mut filter = ExponentialAverage::new(50);
+
+        assert_eq!(filter.update(measure(500, 200)), measure(500, 200));
+        assert_eq!(filter.update(measure(600, 100)), measure(550, 150));
+        assert_eq!(filter.update(measure(600, 100)), measure(575, 125));
+
+        let mut slow = ExponentialAverage::new(1);
+        slow.update(measure(0, 0));
+        let mut last = measure(0, 0);
+        for _ in 0..200 {
+            last = slow.update(measure(1000, 100));
+        }
+        assert!(last.humidity_decipercent > 850);
+        assert!(last.temperature_decicelsius > 85);
+    }
+}
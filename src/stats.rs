@@ -0,0 +1,104 @@
+use defmt::Format;
+
+use crate::classify::BitClassification;
+use crate::raw::RawFrame;
+
+/// Index of the first data bit high pulse in [`RawFrame::pulses`], after the
+/// start pulse, the sensor response delay and the handshake.
+const FIRST_BIT_PULSE: usize = 5;
+
+/// Widths of the high pulses encoding the same bit value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct PulseStats {
+    pub count: u8,
+    pub min_us: u32,
+    pub max_us: u32,
+    pub mean_us: u32,
+}
+
+impl PulseStats {
+    fn from_widths(widths: impl Iterator<Item = u32>) -> Option<Self> {
+        let (mut count, mut sum) = (0u32, 0u32);
+        let (mut min_us, mut max_us) = (u32::MAX, 0);
+        for width in widths {
+            count += 1;
+            sum += width;
+            min_us = min_us.min(width);
+            max_us = max_us.max(width);
+        }
+        (count > 0).then(|| PulseStats {
+            count: count as u8,
+            min_us,
+            max_us,
+            mean_us: sum / count,
+        })
+    }
+}
+
+/// Health of the signal received for a measure, to detect degrading cables or
+/// sensors before they produce invalid frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Format)]
+pub struct SignalStats {
+    /// Delay between the host releasing the line and the sensor pulling it low.
+    pub response_us: u32,
+    /// Width of the low pulse of the handshake, 80µs nominally.
+    pub handshake_low_us: u32,
+    /// Width of the high pulse of the handshake, 80µs nominally.
+    pub handshake_high_us: u32,
+    /// High pulses of the 0 bits, if any.
+    pub zeros: Option<PulseStats>,
+    /// High pulses of the 1 bits, if any.
+    pub ones: Option<PulseStats>,
+    /// Decision threshold, and margin of the closest pulse to it.
+    pub classification: BitClassification,
+    /// Number of times the pin was read while waiting for the edges.
+    pub polls: u32,
+}
+
+impl SignalStats {
+    /// Statistics of a complete frame, whose bits were told apart with
+    /// `classification`.
+    pub(crate) fn new(raw: &RawFrame, classification: BitClassification) -> Self {
+        let mut pulses = raw.pulses().skip(1).map(|pulse| pulse.duration_us);
+        let response_us = pulses.next().unwrap_or(0);
+        let handshake_low_us = pulses.next().unwrap_or(0);
+        let handshake_high_us = pulses.next().unwrap_or(0);
+        let bit_highs = || {
+            raw.pulses()
+                .skip(FIRST_BIT_PULSE)
+                .step_by(2)
+                .map(|pulse| pulse.duration_us)
+        };
+        let threshold_us = classification.threshold_us as u32;
+        Self {
+            response_us,
+            handshake_low_us,
+            handshake_high_us,
+            zeros: PulseStats::from_widths(bit_highs().filter(|&high| high <= threshold_us)),
+            ones: PulseStats::from_widths(bit_highs().filter(|&high| high > threshold_us)),
+            classification,
+            polls: raw.polls(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulse_stats_of_widths() {
+        let stats = PulseStats::from_widths([26, 30, 28, 24].into_iter());
+
+        assert_eq!(
+            stats,
+            Some(PulseStats {
+                count: 4,
+                min_us: 24,
+                max_us: 30,
+                mean_us: 27,
+            })
+        );
+        assert_eq!(PulseStats::from_widths(core::iter::empty()), None);
+    }
+}
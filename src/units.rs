@@ -0,0 +1,126 @@
+//! Typed units for the values of a [`Measure`], so that temperatures and
+//! humidities in different units cannot be mixed up.
+
+use defmt::Format;
+
+use crate::Measure;
+
+/// 0°C in kelvins.
+pub(crate) const ZERO_CELSIUS_KELVIN: f32 = 273.15;
+
+/// Temperature in degree Celsius.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Format)]
+pub struct Celsius(pub f32);
+
+/// Temperature in degree Fahrenheit.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Format)]
+pub struct Fahrenheit(pub f32);
+
+/// Temperature in Kelvin.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Format)]
+pub struct Kelvin(pub f32);
+
+/// Relative humidity in %, from 0 to 100.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Format)]
+pub struct RelativeHumidity(pub f32);
+
+/// Relative humidity as a fraction, from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Format)]
+pub struct HumidityFraction(pub f32);
+
+impl From<Fahrenheit> for Celsius {
+    fn from(value: Fahrenheit) -> Self {
+        Self((value.0 - 32.0) * 5.0 / 9.0)
+    }
+}
+
+impl From<Kelvin> for Celsius {
+    fn from(value: Kelvin) -> Self {
+        Self(value.0 - ZERO_CELSIUS_KELVIN)
+    }
+}
+
+impl From<Celsius> for Fahrenheit {
+    fn from(value: Celsius) -> Self {
+        Self(value.0 * 9.0 / 5.0 + 32.0)
+    }
+}
+
+impl From<Kelvin> for Fahrenheit {
+    fn from(value: Kelvin) -> Self {
+        Celsius::from(value).into()
+    }
+}
+
+impl From<Celsius> for Kelvin {
+    fn from(value: Celsius) -> Self {
+        Self(value.0 + ZERO_CELSIUS_KELVIN)
+    }
+}
+
+impl From<Fahrenheit> for Kelvin {
+    fn from(value: Fahrenheit) -> Self {
+        Celsius::from(value).into()
+    }
+}
+
+impl From<HumidityFraction> for RelativeHumidity {
+    fn from(value: HumidityFraction) -> Self {
+        Self(value.0 * 100.0)
+    }
+}
+
+impl From<RelativeHumidity> for HumidityFraction {
+    fn from(value: RelativeHumidity) -> Self {
+        Self(value.0 / 100.0)
+    }
+}
+
+impl Measure {
+    /// Temperature, converted to any unit with `.into()`.
+    pub fn temperature(&self) -> Celsius {
+        Celsius(self.temperature_f32())
+    }
+
+    /// Relative humidity, converted to a fraction with `.into()`.
+    pub fn humidity(&self) -> RelativeHumidity {
+        RelativeHumidity(self.humidity_f32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_temperatures() {
+        let measure = Measure {
+            humidity_decipercent: 658,
+            temperature_decicelsius: -400,
+        };
+
+        assert_eq!(measure.temperature(), Celsius(-40.0));
+        assert_eq!(Fahrenheit::from(measure.temperature()), Fahrenheit(-40.0));
+        assert_eq!(Kelvin::from(Celsius(25.0)), Kelvin(298.15));
+        assert_eq!(Celsius::from(Fahrenheit(212.0)), Celsius(100.0));
+        assert_eq!(Fahrenheit::from(Kelvin(273.15)), Fahrenheit(32.0));
+    }
+
+    #[test]
+    fn convert_humidities() {
+        let measure = Measure {
+            humidity_decipercent: 500,
+            temperature_decicelsius: 0,
+        };
+
+        assert_eq!(measure.humidity(), RelativeHumidity(50.0));
+        assert_eq!(
+            HumidityFraction::from(measure.humidity()),
+            HumidityFraction(0.5)
+        );
+        assert_eq!(
+            RelativeHumidity::from(HumidityFraction(0.25)),
+            RelativeHumidity(25.0)
+        );
+    }
+}